@@ -0,0 +1,62 @@
+use std::error::Error;
+
+use gloom_rs::{
+    glm, runner,
+    gl_utils::{
+        bindable::Bindable,
+        triangle::Triangle,
+        shaders::program::{Program, ProgramBuilder}
+    },
+    input::actions::Actions,
+    app::{App, AppContext}
+};
+
+// Checkerboard from simple_checker.frag drawn on a quad covering the whole window
+#[derive(Default)]
+struct CheckerApp {
+    quad: Option<Triangle>,
+    program: Option<Program>,
+}
+
+impl App for CheckerApp {
+    fn init(&mut self, _ctx: &AppContext) -> Result<(), Box<dyn Error>> {
+        // Covers all of clip space, so no need for a camera
+        let vertices = [
+            -1.0, -1.0, 0.0,
+             1.0, -1.0, 0.0,
+             1.0,  1.0, 0.0,
+            -1.0,  1.0, 0.0,
+        ];
+        let indices = [0, 1, 2, 0, 2, 3];
+        self.quad = Some(Triangle::init(&vertices, &indices)?);
+
+        let mut program = ProgramBuilder::new()
+            .attach_file("assets/shaders/main.vert")?
+            .attach_file("assets/shaders/simple_checker.frag")?
+            .link()?;
+
+        let identity = glm::identity::<f32, glm::U4>();
+        for uniform in ["projection", "c_trans"].iter() {
+            program.locate_uniform(uniform)
+                .and_then(|_| program.set_uniform(uniform, identity))
+                .map_err(|e| format!("Failed to set {}: {}", uniform, e))?;
+        }
+        self.program = Some(program);
+
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &AppContext, _delta_time: f32, _actions: &Actions) { }
+
+    fn render(&mut self, _ctx: &AppContext) {
+        if let (Some(quad), Some(program)) = (&self.quad, &self.program) {
+            program.bind();
+            quad.draw();
+            program.unbind();
+        }
+    }
+}
+
+fn main() {
+    runner::run_with_args(CheckerApp::default);
+}
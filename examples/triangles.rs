@@ -0,0 +1,166 @@
+use std::error::Error;
+use std::rc::Rc;
+
+use gloom_rs::{
+    glm, gl, runner,
+    gl_utils::{
+        backend,
+        triangle::Triangle,
+        shaders::program::{Program, ProgramBuilder}
+    },
+    scene::node::SceneNode,
+    camera::{Camera, free_fly::FreeFlyCamera, orbit::OrbitCamera},
+    input::actions::Actions,
+    events::RenderEvent,
+    app::{App, AppContext}
+};
+
+// Using Triangle abstraction (See gl_utils::triangle)
+struct TrianglesApp {
+    scene_root: SceneNode,
+    program: Option<Rc<Program>>,
+
+    // Free-fly camera by default, tab switches to orbiting the triangles and F frames them
+    free_fly_camera: FreeFlyCamera,
+    orbit_camera: OrbitCamera,
+    use_orbit_camera: bool,
+    bounds: (glm::Vec3, glm::Vec3),
+
+    wireframe: bool,
+}
+
+impl TrianglesApp {
+    fn new() -> TrianglesApp {
+        TrianglesApp {
+            scene_root: SceneNode::new("root"),
+            program: None,
+            free_fly_camera: FreeFlyCamera::new(glm::vec3(0.0, 0.0, 2.0), 1.0),
+            orbit_camera: OrbitCamera::new(glm::zero(), 2.0, 1.0),
+            use_orbit_camera: false,
+            bounds: (glm::zero(), glm::zero()),
+            wireframe: false,
+        }
+    }
+
+    fn camera(&self) -> &dyn Camera {
+        if self.use_orbit_camera { &self.orbit_camera } else { &self.free_fly_camera }
+    }
+}
+
+impl App for TrianglesApp {
+    fn init(&mut self, ctx: &AppContext) -> Result<(), Box<dyn Error>> {
+        // Keep the cursor inside the window so mouse look doesn't run off screen
+        ctx.set_cursor_grab(true);
+
+        // == // Set up your VAO here
+        // opengl goes from -1 to 1
+        let mut pos: f32 = -1.0;
+        let stride: f32 = 2.0 / 5.0;
+        let mut vertices = Vec::<f32>::new();
+        let mut indices = Vec::<u32>::new();
+        for i in 0..5 {
+            let mut new_triangle = vec![
+                pos, -0.5, 0.0,
+                pos + stride, -0.5, 0.0,
+                pos + stride / 2.0, 0.5, 0.0
+            ];
+
+            vertices.append(&mut new_triangle);
+            let index = i * 3;
+            indices.append(&mut vec![index, index + 1, index + 2]);
+
+            pos += stride;
+        }
+
+        // We could also inline hardcoded 5 triangles, but what's the fun in that ;)
+        // Of course this would lead to easier code to read which is faster and objectively better ...
+        let my_triangle = {
+           Triangle::init(&vertices, &indices)?
+        };
+
+        // Basic usage of shader helper
+        let mut program = ctx.config.renderer.shaders.iter()
+            .try_fold(ProgramBuilder::new(), |builder, shader| builder.attach_file(shader))?
+            .link()?;
+
+        for uniform in ["elapsed", "c_trans", "projection"].iter() {
+            if let Err(e) = program.locate_uniform(uniform) {
+                return Err(format!("Failed to find {}, probably loading wrong shader. err: {}", uniform, e).into());
+            }
+        }
+
+        self.free_fly_camera.set_aspect(ctx.aspect_ratio());
+        self.orbit_camera.set_aspect(ctx.aspect_ratio());
+
+        self.bounds = vertices.chunks(3).fold(
+            (glm::vec3(f32::MAX, f32::MAX, f32::MAX), glm::vec3(f32::MIN, f32::MIN, f32::MIN)),
+            |(min, max), v| {
+                let v = glm::vec3(v[0], v[1], v[2]);
+                (glm::min2(&min, &v), glm::max2(&max, &v))
+            }
+        );
+
+        // Build the scene graph. Every node gets its world transform from its parent, so
+        // animating a node (e.g. a rotor) only requires changing its local transform
+        let program = Rc::new(program);
+        self.scene_root.add_child(SceneNode::with_mesh("triangles", Rc::new(my_triangle), Rc::clone(&program)));
+        self.program = Some(program);
+
+        Ok(())
+    }
+
+    fn on_event(&mut self, ctx: &AppContext, event: &RenderEvent) {
+        if let RenderEvent::Resized { .. } = event {
+            self.free_fly_camera.set_aspect(ctx.aspect_ratio());
+            self.orbit_camera.set_aspect(ctx.aspect_ratio());
+        }
+    }
+
+    fn update(&mut self, ctx: &AppContext, delta_time: f32, actions: &Actions) {
+        if actions.is_pressed("toggle_camera") {
+            self.use_orbit_camera = !self.use_orbit_camera;
+            ctx.set_cursor_grab(!self.use_orbit_camera);
+        }
+        if actions.is_pressed("frame_object") {
+            self.orbit_camera.frame(&self.bounds.0, &self.bounds.1);
+        }
+        if actions.is_pressed("toggle_wireframe") {
+            self.wireframe = !self.wireframe;
+            backend::current().polygon_mode(gl::FRONT_AND_BACK, if self.wireframe { gl::LINE } else { gl::FILL });
+        }
+
+        let camera: &mut dyn Camera = if self.use_orbit_camera {
+            &mut self.orbit_camera
+        } else {
+            &mut self.free_fly_camera
+        };
+        camera.update(actions, delta_time);
+
+        self.scene_root.update_world_transforms(&glm::identity());
+    }
+
+    fn render(&mut self, ctx: &AppContext) {
+        let program = match &self.program {
+            Some(program) => program,
+            None => return,
+        };
+
+        if let Err(e) = program.set_uniform("elapsed", ctx.elapsed) {
+            eprintln!("{}", e)
+        };
+
+        let projection = self.camera().projection();
+        if let Err(e) = program.set_uniform("projection", projection) {
+            eprintln!("{}", e);
+        };
+
+        let _scene = ctx.profiler.scope("scene");
+        if let Err(e) = self.scene_root.draw(&self.camera().view()) {
+            eprintln!("{}", e)
+        };
+    }
+}
+
+fn main() {
+    runner::run_with_args(TrianglesApp::new);
+}
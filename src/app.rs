@@ -0,0 +1,63 @@
+use std::error::Error;
+
+use glutin::event_loop::EventLoopProxy;
+
+use crate::config::Config;
+use crate::events::{RenderEvent, RunnerEvent};
+use crate::gl_utils::{capabilities::Capabilities, profiler::GpuProfiler};
+use crate::input::actions::Actions;
+
+// What the runner lends an app in every hook. Only valid for the duration of the call
+pub struct AppContext<'a> {
+    // Asks the event loop to change the window. None when rendering headless
+    pub runner: Option<&'a EventLoopProxy<RunnerEvent>>,
+    pub config: &'a Config,
+    // Check these before using optional GL features
+    pub capabilities: &'a Capabilities,
+    // Wrap passes in profiler.scope("name") to see their GPU time. Does nothing unless enabled
+    // in the config
+    pub profiler: &'a GpuProfiler,
+    // Size of the drawable area in physical pixels
+    pub window_size: (u32, u32),
+    // Physical pixels per logical pixel, for sizing things like text and UI. Always 1 headless
+    pub scale_factor: f64,
+    // Seconds since the first frame
+    pub elapsed: f32,
+}
+
+impl<'a> AppContext<'a> {
+    pub fn aspect_ratio(&self) -> f32 {
+        self.window_size.0 as f32 / self.window_size.1.max(1) as f32
+    }
+
+    // Keep the cursor inside the window and hide it, e.g. for mouse look. The event loop applies
+    // this after the hook returns, and it does nothing headless
+    pub fn set_cursor_grab(&self, grab: bool) {
+        if let Some(runner) = self.runner {
+            // Fails if the event loop is already gone, and then there's no window left to change
+            let _ = runner.send_event(RunnerEvent::SetCursorGrab(grab));
+        }
+    }
+}
+
+// Implement this and hand it to runner::run instead of editing the render loop.
+// All hooks are called on the render thread with the OpenGL context current. In headless mode
+// there is no window, no input, and time advances by a fixed step every frame.
+pub trait App {
+    // Called once before the first frame. Create GL resources here
+    fn init(&mut self, ctx: &AppContext) -> Result<(), Box<dyn Error>>;
+
+    // Called every frame before render, with the time since the last frame in seconds
+    fn update(&mut self, ctx: &AppContext, delta_time: f32, actions: &Actions);
+
+    // Called every frame after the screen has been cleared, before swapping buffers
+    fn render(&mut self, ctx: &AppContext);
+
+    // Called for every event forwarded from the event loop, before update.
+    // The runner has already updated the viewport when this gets a resize
+    fn on_event(&mut self, _ctx: &AppContext, _event: &RenderEvent) { }
+
+    // Called once after the last frame, when the window is closing. The app is dropped right after,
+    // with the context still current, so GL objects can clean up after themselves in Drop
+    fn shutdown(&mut self, _ctx: &AppContext) { }
+}
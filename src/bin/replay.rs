@@ -0,0 +1,110 @@
+// Replays a GL trace saved with --trace into an offscreen framebuffer, saves the resulting image
+// and prints the GL state at the end, to compare what different machines make of the same calls
+use std::{env, error::Error, process};
+
+use gloom_rs::{
+    gl_utils::{
+        backend::NativeGl,
+        bindable::Bindable,
+        capabilities::Capabilities,
+        framebuffer::Framebuffer
+    },
+    headless::HeadlessContext,
+    screenshot,
+    trace::{self, GlState, Summary, Trace}
+};
+
+const USAGE: &str = "\
+Usage: cargo run --bin replay -- <trace> [options]
+
+Options:
+    --output <path>   Save the replayed frame here (default: replay.png)
+    --list            Print the calls of the traced frame
+    -h, --help        Print this message";
+
+struct Options {
+    trace: String,
+    output: String,
+    list: bool,
+}
+
+// None if help was asked for. Values are consumed with their flag, so --output -h saves to "-h"
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut trace = None;
+    let mut output = "replay.png".to_string();
+    let mut list = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--output" => output = args.next().ok_or("--output needs a value")?.clone(),
+            "--list" => list = true,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            path if trace.is_none() => trace = Some(path.to_string()),
+            extra => return Err(format!("Unexpected argument {}", extra)),
+        }
+    }
+
+    Ok(Some(Options { trace: trace.ok_or("No trace given")?, output, list }))
+}
+
+fn replay(options: &Options) -> Result<(), Box<dyn Error>> {
+    let trace = Trace::load(&options.trace)?;
+    println!("Frame {} at {}x{}, {} calls, {} of them in the frame",
+        trace.frame, trace.width, trace.height, trace.calls.len(), trace.frame_calls().len());
+    println!("Captured on {} ({})", trace.renderer, trace.gl_version);
+    if options.list {
+        for (index, call) in trace.frame_calls().iter().enumerate() {
+            println!("{:>8}  {}", trace.frame_start + index, Summary(call));
+        }
+    }
+
+    let context = HeadlessContext::new(false)?;
+    let capabilities = Capabilities::query();
+    println!("Replaying on {} ({})", capabilities.renderer, capabilities.version);
+
+    let framebuffer = Framebuffer::new(trace.width, trace.height, 0)?;
+    framebuffer.bind();
+    let report = trace::replay(&trace.calls, &NativeGl);
+    let image = framebuffer.read_pixels()?;
+    screenshot::save(&image, &options.output)?;
+    println!("Saved the replayed frame to {}", options.output);
+
+    println!("\nState after the frame:\n{}", GlState::query(&report.programs));
+    if !report.warnings.is_empty() {
+        println!("{} warnings while replaying:", report.warnings.len());
+        for warning in &report.warnings {
+            println!("    {}", warning);
+        }
+    }
+
+    drop(framebuffer);
+    drop(context);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = replay(&options) {
+        eprintln!("Replay failed: {}", e);
+        let mut source = e.source();
+        while let Some(cause) = source {
+            eprintln!("    caused by: {}", cause);
+            source = cause.source();
+        }
+        process::exit(1);
+    }
+}
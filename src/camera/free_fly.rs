@@ -0,0 +1,109 @@
+use crate::input::actions::Actions;
+use super::Camera;
+
+const MIN_FOV: f32 = 0.1;
+const MAX_FOV: f32 = 2.8;
+// Just shy of straight up/down, looking straight up flips the view because of the up vector
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+// Free-fly camera, moved with the move_* axes (WASD + QE by default) and rotated with the mouse.
+// A yaw and pitch of zero looks down the negative z axis, same as OpenGL's default.
+pub struct FreeFlyCamera {
+    pub position: glm::Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+
+    // Units per second
+    pub speed: f32,
+    // Radians per pixel of mouse movement
+    pub sensitivity: f32,
+
+    // Vertical field of view in radians
+    pub fov: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl FreeFlyCamera {
+    pub fn new(position: glm::Vec3, aspect: f32) -> FreeFlyCamera {
+        FreeFlyCamera {
+            position,
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 2.0,
+            sensitivity: 0.002,
+            fov: 1.4,
+            aspect,
+            near: 0.1,
+            far: 40.0,
+        }
+    }
+
+    pub fn forward(&self) -> glm::Vec3 {
+        glm::vec3(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            -self.yaw.cos() * self.pitch.cos()
+        )
+    }
+
+    pub fn right(&self) -> glm::Vec3 {
+        glm::normalize(&glm::cross(&self.forward(), &glm::Vec3::y()))
+    }
+
+    // Move the camera along its own axes, each going from -1 to 1
+    pub fn handle_movement(&mut self, forward: f32, right: f32, up: f32, delta_time: f32) {
+        let direction = self.forward() * forward + self.right() * right + glm::Vec3::y() * up;
+
+        // Avoid moving faster diagonally
+        if glm::length(&direction) > 0.0 {
+            self.position += glm::normalize(&direction) * self.speed * delta_time;
+        }
+    }
+
+    // Rotate the camera by a mouse movement given in pixels
+    pub fn handle_mouse(&mut self, delta_x: f32, delta_y: f32) {
+        self.yaw += delta_x * self.sensitivity;
+        self.pitch = (self.pitch - delta_y * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn adjust_speed(&mut self, factor: f32) {
+        self.speed *= factor;
+    }
+
+    pub fn adjust_fov(&mut self, delta: f32) {
+        self.fov = (self.fov + delta).clamp(MIN_FOV, MAX_FOV);
+    }
+
+}
+
+impl Camera for FreeFlyCamera {
+    fn update(&mut self, actions: &Actions, delta_time: f32) {
+        self.handle_movement(
+            actions.axis("move_forward"),
+            actions.axis("move_right"),
+            actions.axis("move_up"),
+            delta_time
+        );
+
+        // Speed and zoom scale smoothly while the keys are held
+        self.adjust_speed((1.0 + delta_time).powf(actions.axis("camera_speed")));
+        self.adjust_fov(actions.axis("camera_fov") * delta_time);
+
+        let (delta_x, delta_y) = actions.input.mouse_delta();
+        self.handle_mouse(delta_x, delta_y);
+    }
+
+    fn view(&self) -> glm::Mat4 {
+        glm::look_at(&self.position, &(self.position + self.forward()), &glm::Vec3::y())
+    }
+
+    fn projection(&self) -> glm::Mat4 {
+        glm::perspective(self.aspect, self.fov, self.near, self.far)
+    }
+
+    fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+}
@@ -0,0 +1,12 @@
+pub mod free_fly;
+pub mod orbit;
+
+use crate::input::actions::Actions;
+
+// Common interface for all camera types so the render loop doesn't care which one is active
+pub trait Camera {
+    fn update(&mut self, actions: &Actions, delta_time: f32);
+    fn view(&self) -> glm::Mat4;
+    fn projection(&self) -> glm::Mat4;
+    fn set_aspect(&mut self, aspect: f32);
+}
@@ -0,0 +1,149 @@
+use crate::input::actions::Actions;
+use super::Camera;
+
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+const MIN_DISTANCE: f32 = 0.05;
+
+// Camera that orbits around a target point, meant for inspecting a model.
+// Drag with orbit_rotate (left mouse button) to rotate, hold orbit_pan (shift) while dragging to pan
+// and scroll to zoom.
+// All movement is applied to a goal state which the camera eases towards, so it moves smoothly.
+pub struct OrbitCamera {
+    pub target: glm::Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+
+    target_goal: glm::Vec3,
+    distance_goal: f32,
+    yaw_goal: f32,
+    pitch_goal: f32,
+
+    // Radians per pixel dragged
+    pub rotate_sensitivity: f32,
+    // Fraction of the distance to the target moved per pixel dragged
+    pub pan_sensitivity: f32,
+    // Factor the distance is multiplied with per line scrolled towards the target
+    pub zoom_factor: f32,
+    // How quickly the camera catches up to its goal, higher is snappier. Zero disables damping
+    pub damping: f32,
+
+    pub fov: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl OrbitCamera {
+    pub fn new(target: glm::Vec3, distance: f32, aspect: f32) -> OrbitCamera {
+        OrbitCamera {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            target_goal: target,
+            distance_goal: distance,
+            yaw_goal: 0.0,
+            pitch_goal: 0.0,
+            rotate_sensitivity: 0.005,
+            pan_sensitivity: 0.0015,
+            zoom_factor: 0.9,
+            damping: 12.0,
+            fov: 1.4,
+            aspect,
+            near: 0.1,
+            far: 40.0,
+        }
+    }
+
+    pub fn eye(&self) -> glm::Vec3 {
+        let offset = glm::vec3(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos()
+        );
+        self.target + offset * self.distance
+    }
+
+    pub fn rotate(&mut self, delta_x: f32, delta_y: f32) {
+        self.yaw_goal -= delta_x * self.rotate_sensitivity;
+        self.pitch_goal = (self.pitch_goal + delta_y * self.rotate_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn pan(&mut self, delta_x: f32, delta_y: f32) {
+        let forward = glm::normalize(&(self.target - self.eye()));
+        let right = glm::normalize(&glm::cross(&forward, &glm::Vec3::y()));
+        let up = glm::cross(&right, &forward);
+
+        let scale = self.distance_goal * self.pan_sensitivity;
+        self.target_goal += (up * delta_y - right * delta_x) * scale;
+    }
+
+    pub fn zoom(&mut self, lines: f32) {
+        self.distance_goal = (self.distance_goal * self.zoom_factor.powf(lines)).max(MIN_DISTANCE);
+    }
+
+    // Move the target to the center of the bounding box and back off until the whole box is in view
+    pub fn frame(&mut self, min: &glm::Vec3, max: &glm::Vec3) {
+        let center = (min + max) * 0.5;
+        let radius = (glm::length(&(max - min)) * 0.5).max(MIN_DISTANCE);
+
+        // Fit the bounding sphere to the narrowest of the vertical and horizontal field of view
+        let horizontal_fov = 2.0 * ((self.fov * 0.5).tan() * self.aspect).atan();
+        let fov = self.fov.min(horizontal_fov);
+
+        self.target_goal = center;
+        self.distance_goal = radius / (fov * 0.5).sin();
+        self.far = self.far.max(self.distance_goal + radius * 2.0);
+    }
+
+    // Jump straight to the goal state, skipping any damping
+    pub fn snap(&mut self) {
+        self.target = self.target_goal;
+        self.distance = self.distance_goal;
+        self.yaw = self.yaw_goal;
+        self.pitch = self.pitch_goal;
+    }
+}
+
+impl Camera for OrbitCamera {
+    fn update(&mut self, actions: &Actions, delta_time: f32) {
+        if actions.is_down("orbit_rotate") {
+            let (delta_x, delta_y) = actions.input.mouse_delta();
+            if actions.is_down("orbit_pan") {
+                self.pan(delta_x, delta_y);
+            } else {
+                self.rotate(delta_x, delta_y);
+            }
+        }
+
+        let (_, scroll) = actions.input.scroll();
+        if scroll != 0.0 {
+            self.zoom(scroll);
+        }
+
+        if self.damping <= 0.0 {
+            self.snap();
+            return;
+        }
+
+        // Frame rate independent exponential smoothing towards the goal
+        let t = 1.0 - (-self.damping * delta_time).exp();
+        self.target = glm::lerp(&self.target, &self.target_goal, t);
+        self.distance += (self.distance_goal - self.distance) * t;
+        self.yaw += (self.yaw_goal - self.yaw) * t;
+        self.pitch += (self.pitch_goal - self.pitch) * t;
+    }
+
+    fn view(&self) -> glm::Mat4 {
+        glm::look_at(&self.eye(), &self.target, &glm::Vec3::y())
+    }
+
+    fn projection(&self) -> glm::Mat4 {
+        glm::perspective(self.aspect, self.fov, self.near, self.far)
+    }
+
+    fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+}
@@ -0,0 +1,485 @@
+use std::{fmt, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::display::{DisplaySettings, VideoModeRequest, WindowMode};
+use crate::gl_utils::debug::DebugOutputConfig;
+use crate::input::bindings::DEFAULT_BINDINGS_PATH;
+use crate::profiling::CpuProfileConfig;
+use crate::recording::RecordingConfig;
+use crate::stats::StatsConfig;
+use crate::trace::TraceConfig;
+
+pub const DEFAULT_CONFIG_PATH: &str = "assets/config/config.toml";
+
+pub const USAGE: &str = "\
+Usage: cargo run --example <name> -- [options]
+
+Options:
+    --config <path>       Config file to load (default: assets/config/config.toml)
+    --title <title>       Window title
+    --width <pixels>      Window width
+    --height <pixels>     Window height
+    --windowed            Start in a window
+    --fullscreen          Start in borderless fullscreen
+    --exclusive           Start in exclusive fullscreen
+    --monitor <index>     Monitor to use for fullscreen
+    --vsync, --no-vsync   Enable or disable vsync
+    --msaa <samples>      Multisampling samples, 0 disables it
+    --debug, --no-debug   Enable or disable OpenGL debug output
+    --bindings <path>     Key bindings file
+    --crash-report <path> Write a report here if the render thread crashes
+    --gl-info             Print the OpenGL version, limits and extensions at startup
+    --headless            Render without a window, then exit
+    --frames <count>      Frames to render in headless mode
+    --start-time <secs>   Elapsed time of the first headless frame
+    --output <path>       Save the last headless frame as an image
+    --record              Record every frame at a fixed timestep
+    --record-frames <n>   Stop after recording this many frames
+    --record-fps <rate>   Frame rate of the recording
+    --trace <path>        Save a trace of every GL call up to the end of a frame
+    --trace-frame <n>     Frame to trace, counting from 0
+    --show-fps            Show the frame rate in the window title
+    --frame-times <path>  Write the time of every frame to a CSV file
+    --benchmark <frames>  Render this many frames, print frame time statistics and exit
+    --warmup <frames>     Frames to render before the benchmark starts measuring
+    --gpu-profile         Measure the GPU time of every pass and print it every second
+    --cpu-profile <path>  Save CPU spans of the first frames as a Chrome trace
+    --profile-frames <n>  Frames to include in the CPU profile
+    -h, --help            Print this message";
+
+// Everything that used to be hard coded in main.rs. Every field has a default, so the config file
+// only needs to contain what it changes and a missing file gives the same result as an empty one
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub context: ContextConfig,
+    pub renderer: RendererConfig,
+    pub input: InputConfig,
+    pub diagnostics: DiagnosticsConfig,
+    // Only used with a debug context
+    pub debug_output: DebugOutputConfig,
+    pub headless: HeadlessConfig,
+    pub screenshots: ScreenshotConfig,
+    pub recording: RecordingConfig,
+    pub trace: TraceConfig,
+    pub stats: StatsConfig,
+    pub profiler: ProfilerConfig,
+    pub cpu_profile: CpuProfileConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub mode: WindowMode,
+    pub monitor: Option<usize>,
+    pub video_mode: Option<VideoModeRequest>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            title: "Gloom-rs".to_string(),
+            width: 800,
+            height: 600,
+            mode: WindowMode::Windowed,
+            monitor: None,
+            video_mode: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContextConfig {
+    pub vsync: bool,
+    // Samples per pixel, 0 disables multisampling
+    pub msaa: u16,
+    // Request a debug context and handle its messages as configured in debug_output
+    pub debug: bool,
+}
+
+impl Default for ContextConfig {
+    fn default() -> ContextConfig {
+        ContextConfig {
+            vsync: true,
+            msaa: 0,
+            debug: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RendererConfig {
+    pub clear_color: [f32; 4],
+    // Shader files linked into the main program, the type is decided by the file extension
+    pub shaders: Vec<String>,
+}
+
+impl Default for RendererConfig {
+    fn default() -> RendererConfig {
+        RendererConfig {
+            clear_color: [0.163, 0.163, 0.163, 1.0],
+            shaders: vec![
+                "assets/shaders/main.vert".to_string(),
+                "assets/shaders/main.frag".to_string(),
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub bindings: String,
+}
+
+impl Default for InputConfig {
+    fn default() -> InputConfig {
+        InputConfig {
+            bindings: DEFAULT_BINDINGS_PATH.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    // Where to write a report when the render thread crashes. A timestamp is added to the file name
+    pub crash_report: Option<String>,
+    // Print the capabilities of the context once it has been created
+    pub gl_info: bool,
+}
+
+// Rendering without a window, for machines without a display. The window size is used as the
+// framebuffer size
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeadlessConfig {
+    pub enabled: bool,
+    pub frames: u32,
+    // Frames are rendered with a fixed timestep of 1 / frame_rate, so every run is the same. While
+    // recording, the recording frame rate is used instead
+    pub frame_rate: f32,
+    // The elapsed time of the first frame, to render a specific moment of an animation
+    pub start_time: f32,
+    // Where to save the last frame, the format is decided by the file extension
+    pub output: Option<String>,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> HeadlessConfig {
+        HeadlessConfig {
+            enabled: false,
+            frames: 1,
+            frame_rate: 60.0,
+            start_time: 0.0,
+            output: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenshotConfig {
+    // Screenshots are saved here with a timestamp in the name
+    pub directory: String,
+    // Keep the alpha channel of the framebuffer instead of saving an opaque image
+    pub keep_alpha: bool,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> ScreenshotConfig {
+        ScreenshotConfig {
+            directory: "screenshots".to_string(),
+            keep_alpha: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfilerConfig {
+    // Time passes on the GPU with timer queries
+    pub enabled: bool,
+    // Seconds between printing the latest frame's GPU times, 0 never prints them
+    pub print_interval: f32,
+}
+
+impl Default for ProfilerConfig {
+    fn default() -> ProfilerConfig {
+        ProfilerConfig {
+            enabled: false,
+            print_interval: 1.0,
+        }
+    }
+}
+
+// A command line override, parsed up front and applied once the config file is loaded
+type Override = Box<dyn FnOnce(&mut Config)>;
+
+// What the command line asks for
+#[derive(Debug)]
+pub enum Command {
+    Run(Box<Config>),
+    Help,
+}
+
+impl Config {
+    // Load the config file and apply command line overrides on top, then validate the result.
+    // Expects the arguments without the program name. The arguments are read in a single pass
+    // that consumes each flag's value, so a value that looks like a flag is never mistaken for one
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, ConfigError> {
+        let mut config_path = None;
+        let mut overrides = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                // The config file has to be loaded before the other flags can override it
+                "--config" => config_path = Some(args.next().ok_or(ConfigError::MissingValue(flag))?),
+                _ => overrides.push(Config::parse_flag(flag, &mut args)?),
+            }
+        }
+
+        let mut config = match &config_path {
+            Some(path) => Config::load(path)?,
+            None => Config::load_or_default(DEFAULT_CONFIG_PATH)?,
+        };
+        for apply in overrides {
+            apply(&mut config);
+        }
+        config.validate()?;
+        Ok(Command::Run(Box::new(config)))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(path.display().to_string(), e))?;
+        toml::from_str(&src)
+            .map_err(|e| ConfigError::Parse(path.display().to_string(), e))
+    }
+
+    // Like load, but a missing file gives the default config
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        match Config::load(path) {
+            Err(ConfigError::Io(_, e)) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            result => result,
+        }
+    }
+
+    // Apply overrides to this config. Only from_args knows about --config and --help
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), ConfigError> {
+        let mut args = args.iter().cloned();
+        while let Some(flag) = args.next() {
+            Config::parse_flag(flag, &mut args)?(self);
+        }
+        Ok(())
+    }
+
+    // Take the flag's value from args if it has one, and check it before anything is loaded
+    fn parse_flag<I: Iterator<Item = String>>(flag: String, args: &mut I) -> Result<Override, ConfigError> {
+        let mut value = || args.next().ok_or_else(|| ConfigError::MissingValue(flag.clone()));
+
+        let apply: Override = match flag.as_str() {
+            "--title" => {
+                let title = value()?;
+                Box::new(move |c: &mut Config| c.window.title = title)
+            },
+            "--width" => {
+                let width = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.window.width = width)
+            },
+            "--height" => {
+                let height = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.window.height = height)
+            },
+            "--windowed" => Box::new(|c: &mut Config| c.window.mode = WindowMode::Windowed),
+            "--fullscreen" => Box::new(|c: &mut Config| c.window.mode = WindowMode::Borderless),
+            "--exclusive" => Box::new(|c: &mut Config| c.window.mode = WindowMode::Exclusive),
+            "--monitor" => {
+                let monitor = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.window.monitor = Some(monitor))
+            },
+            "--vsync" => Box::new(|c: &mut Config| c.context.vsync = true),
+            "--no-vsync" => Box::new(|c: &mut Config| c.context.vsync = false),
+            "--msaa" => {
+                let msaa = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.context.msaa = msaa)
+            },
+            "--debug" => Box::new(|c: &mut Config| c.context.debug = true),
+            "--no-debug" => Box::new(|c: &mut Config| c.context.debug = false),
+            "--bindings" => {
+                let path = value()?;
+                Box::new(move |c: &mut Config| c.input.bindings = path)
+            },
+            "--crash-report" => {
+                let path = value()?;
+                Box::new(move |c: &mut Config| c.diagnostics.crash_report = Some(path))
+            },
+            "--gl-info" => Box::new(|c: &mut Config| c.diagnostics.gl_info = true),
+            "--headless" => Box::new(|c: &mut Config| c.headless.enabled = true),
+            "--frames" => {
+                let frames = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.headless.frames = frames)
+            },
+            "--start-time" => {
+                let start_time = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.headless.start_time = start_time)
+            },
+            "--output" => {
+                let path = value()?;
+                Box::new(move |c: &mut Config| c.headless.output = Some(path))
+            },
+            "--record" => Box::new(|c: &mut Config| c.recording.enabled = true),
+            "--record-frames" => {
+                let frames = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.recording.frames = Some(frames))
+            },
+            "--record-fps" => {
+                let frame_rate = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.recording.frame_rate = frame_rate)
+            },
+            "--trace" => {
+                let path = value()?;
+                Box::new(move |c: &mut Config| {
+                    c.trace.enabled = true;
+                    c.trace.output = path;
+                })
+            },
+            "--trace-frame" => {
+                let frame = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.trace.frame = frame)
+            },
+            "--show-fps" => Box::new(|c: &mut Config| c.stats.show_in_title = true),
+            "--frame-times" => {
+                let path = value()?;
+                Box::new(move |c: &mut Config| c.stats.csv = Some(path))
+            },
+            "--benchmark" => {
+                let frames = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.stats.benchmark = Some(frames))
+            },
+            "--warmup" => {
+                let frames = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.stats.warmup = frames)
+            },
+            "--gpu-profile" => Box::new(|c: &mut Config| c.profiler.enabled = true),
+            "--cpu-profile" => {
+                let path = value()?;
+                Box::new(move |c: &mut Config| {
+                    c.cpu_profile.enabled = true;
+                    c.cpu_profile.output = path;
+                })
+            },
+            "--profile-frames" => {
+                let frames = parse_value(&flag, &value()?)?;
+                Box::new(move |c: &mut Config| c.cpu_profile.frames = frames)
+            },
+            _ => return Err(ConfigError::UnknownFlag(flag)),
+        };
+        Ok(apply)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+
+        if self.window.width == 0 || self.window.height == 0 {
+            return invalid(format!("window size must be positive, got {}x{}", self.window.width, self.window.height));
+        }
+        if self.context.msaa != 0 && (!self.context.msaa.is_power_of_two() || self.context.msaa > 16) {
+            return invalid(format!("msaa must be 0 or a power of two up to 16, got {}", self.context.msaa));
+        }
+        if self.renderer.clear_color.iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return invalid(format!("clear_color components must be between 0 and 1, got {:?}", self.renderer.clear_color));
+        }
+        if self.headless.frames == 0 {
+            return invalid("headless mode needs to render at least one frame".to_string());
+        }
+        if !self.headless.frame_rate.is_finite() || self.headless.frame_rate <= 0.0 {
+            return invalid(format!("headless frame_rate must be positive, got {}", self.headless.frame_rate));
+        }
+        if !self.headless.start_time.is_finite() {
+            return invalid(format!("headless start_time must be a number, got {}", self.headless.start_time));
+        }
+        if !self.recording.frame_rate.is_finite() || self.recording.frame_rate <= 0.0 {
+            return invalid(format!("recording frame_rate must be positive, got {}", self.recording.frame_rate));
+        }
+        if self.recording.encoder.as_ref().is_some_and(|encoder| encoder.is_empty()) {
+            return invalid("recording encoder needs a command".to_string());
+        }
+        if self.stats.window == 0 {
+            return invalid("stats window needs at least one frame".to_string());
+        }
+        if !self.stats.title_interval.is_finite() || self.stats.title_interval <= 0.0 {
+            return invalid(format!("stats title_interval must be positive, got {}", self.stats.title_interval));
+        }
+        if self.stats.benchmark == Some(0) {
+            return invalid("a benchmark needs to measure at least one frame".to_string());
+        }
+        if !self.profiler.print_interval.is_finite() || self.profiler.print_interval < 0.0 {
+            return invalid(format!("profiler print_interval can't be negative, got {}", self.profiler.print_interval));
+        }
+        if self.cpu_profile.frames == 0 {
+            return invalid("a CPU profile needs to include at least one frame".to_string());
+        }
+        if self.renderer.shaders.is_empty() {
+            return invalid("at least one shader is required".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn display_settings(&self) -> DisplaySettings {
+        DisplaySettings {
+            mode: self.window.mode,
+            monitor: self.window.monitor,
+            video_mode: self.window.video_mode,
+            vsync: self.context.vsync,
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::BadValue(flag.to_string(), value.to_string()))
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, io::Error),
+    Parse(String, toml::de::Error),
+    UnknownFlag(String),
+    MissingValue(String),
+    BadValue(String, String),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, _) => write!(f, "Failed to read config file {}", path),
+            ConfigError::Parse(path, _) => write!(f, "Failed to parse config file {}", path),
+            ConfigError::UnknownFlag(flag) => write!(f, "Unknown option {}, see --help", flag),
+            ConfigError::MissingValue(flag) => write!(f, "Option {} requires a value", flag),
+            ConfigError::BadValue(flag, value) => write!(f, "Invalid value \"{}\" for option {}", value, flag),
+            ConfigError::Invalid(message) => write!(f, "Invalid config: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, e) => Some(e),
+            ConfigError::Parse(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::{any::Any, backtrace::Backtrace, cell::Cell, error::Error, fmt, io, panic, path::Path, sync::Once};
+use std::panic::AssertUnwindSafe;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CrashKind {
+    Panic,
+    Error,
+}
+
+// Everything we know about why a thread died, in a form that can be sent to another thread
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    pub kind: CrashKind,
+    pub thread: String,
+    pub message: String,
+    // The source chain of an error, outermost first. Empty for panics
+    pub causes: Vec<String>,
+    // Where the panic happened, as file:line:column
+    pub location: Option<String>,
+    // Where a panic was raised. Errors have none, since the report is made far from where they
+    // happened and a backtrace would only show the report site
+    pub backtrace: Option<String>,
+}
+
+impl CrashReport {
+    // Capture an error and its sources from the current thread
+    pub fn from_error(error: &dyn Error) -> CrashReport {
+        let mut causes = Vec::new();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            causes.push(cause.to_string());
+            source = cause.source();
+        }
+
+        CrashReport {
+            kind: CrashKind::Error,
+            thread: current_thread_name(),
+            message: error.to_string(),
+            causes,
+            location: None,
+            backtrace: None,
+        }
+    }
+
+    // Write the report to a file, appending a timestamp to the file name so old reports are kept
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let path = path.as_ref();
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("crash");
+        let file_name = match path.extension().and_then(|s| s.to_str()) {
+            Some(extension) => format!("{}-{}.{}", stem, timestamp, extension),
+            None => format!("{}-{}", stem, timestamp),
+        };
+        let path = path.with_file_name(file_name);
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(&path, self.to_string())?;
+        Ok(path.display().to_string())
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            CrashKind::Panic => "panicked",
+            CrashKind::Error => "failed",
+        };
+        write!(f, "Thread '{}' {}", self.thread, what)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        writeln!(f, ": {}", self.message)?;
+        for cause in self.causes.iter() {
+            writeln!(f, "  caused by: {}", cause)?;
+        }
+        if let Some(backtrace) = &self.backtrace {
+            writeln!(f, "Backtrace:")?;
+            write!(f, "{}", backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    // Set while inside catch, so the panic hook knows to capture instead of printing
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    static LAST_PANIC: Cell<Option<CrashReport>> = const { Cell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+// Run a closure, turning a panic into a report with the message and backtrace. Panics inside
+// the closure aren't printed by the default hook, the caller is expected to report them
+pub fn catch<T, F: FnOnce() -> T>(f: F) -> Result<T, Box<CrashReport>> {
+    install_panic_hook();
+
+    let was_catching = CATCHING.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|catching| catching.set(was_catching));
+
+    result.map_err(|payload| {
+        // The hook doesn't run if the panic came from resume_unwind, so fall back to the payload
+        Box::new(LAST_PANIC.with(|last| last.take()).unwrap_or_else(|| CrashReport {
+            kind: CrashKind::Panic,
+            thread: current_thread_name(),
+            message: payload_message(payload.as_ref()),
+            causes: Vec::new(),
+            location: None,
+            backtrace: None,
+        }))
+    })
+}
+
+fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !CATCHING.with(|catching| catching.get()) {
+                return default_hook(info);
+            }
+
+            let report = CrashReport {
+                kind: CrashKind::Panic,
+                thread: current_thread_name(),
+                message: payload_message(info.payload()),
+                causes: Vec::new(),
+                location: info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+                backtrace: Some(Backtrace::force_capture().to_string()),
+            };
+            LAST_PANIC.with(|last| last.set(Some(report)));
+        }));
+    });
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+fn current_thread_name() -> String {
+    std::thread::current().name().unwrap_or("<unnamed>").to_string()
+}
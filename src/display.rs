@@ -0,0 +1,195 @@
+use std::{ffi::c_void, fmt};
+
+use serde::Deserialize;
+use glutin::{
+    Context, PossiblyCurrent,
+    monitor::{MonitorHandle, VideoMode},
+    platform::ContextTraitExt,
+    window::{Fullscreen, Window}
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    Windowed,
+    // Fullscreen window covering the monitor, fast to switch to and from
+    Borderless,
+    // Takes over the monitor and changes its video mode
+    Exclusive,
+}
+
+impl WindowMode {
+    pub fn next(self) -> WindowMode {
+        match self {
+            WindowMode::Windowed => WindowMode::Borderless,
+            WindowMode::Borderless => WindowMode::Exclusive,
+            WindowMode::Exclusive => WindowMode::Windowed,
+        }
+    }
+}
+
+// Preferred video mode for exclusive fullscreen. The closest one the monitor supports is used
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VideoModeRequest {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplaySettings {
+    pub mode: WindowMode,
+    // Index into the available monitors, None uses the monitor the window is currently on
+    pub monitor: Option<usize>,
+    // None uses the largest and fastest mode of the monitor
+    pub video_mode: Option<VideoModeRequest>,
+    pub vsync: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> DisplaySettings {
+        DisplaySettings {
+            mode: WindowMode::Windowed,
+            monitor: None,
+            video_mode: None,
+            vsync: true,
+        }
+    }
+}
+
+impl DisplaySettings {
+    // Switch the window to the configured mode. Has to be called on the thread running the event loop
+    pub fn apply_window_mode(&self, window: &Window) -> Result<(), DisplayError> {
+        let fullscreen = match self.mode {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(self.find_monitor(window)?)),
+            WindowMode::Exclusive => Some(Fullscreen::Exclusive(self.find_video_mode(window)?)),
+        };
+        window.set_fullscreen(fullscreen);
+        Ok(())
+    }
+
+    fn find_monitor(&self, window: &Window) -> Result<MonitorHandle, DisplayError> {
+        match self.monitor {
+            Some(index) => window.available_monitors()
+                .nth(index)
+                .ok_or(DisplayError::NoSuchMonitor(index)),
+            None => Ok(window.current_monitor()),
+        }
+    }
+
+    fn find_video_mode(&self, window: &Window) -> Result<VideoMode, DisplayError> {
+        let monitor = self.find_monitor(window)?;
+        let modes = monitor.video_modes();
+
+        let best = match self.video_mode {
+            // Prefer matching the size over the refresh rate
+            Some(request) => modes.min_by_key(|mode| {
+                let size = mode.size();
+                let size_difference = (size.width as i64 - request.width as i64).abs()
+                    + (size.height as i64 - request.height as i64).abs();
+                let refresh_difference = (mode.refresh_rate() as i64 - request.refresh_rate as i64).abs();
+                (size_difference, refresh_difference)
+            }),
+            None => modes.max_by_key(|mode| {
+                let size = mode.size();
+                (size.width * size.height, mode.refresh_rate(), mode.bit_depth())
+            }),
+        };
+
+        best.ok_or(DisplayError::NoVideoModes)
+    }
+}
+
+type SwapIntervalFn = unsafe extern "system" fn(i32) -> i32;
+type EglSwapIntervalFn = unsafe extern "system" fn(*const c_void, i32) -> u32;
+
+// The platform functions disagree on what they return, so keep track of which one we have
+enum SwapInterval {
+    // Returns true on success
+    Wgl(SwapIntervalFn),
+    // Returns zero on success
+    Glx(SwapIntervalFn),
+    // Returns true on success
+    Egl(EglSwapIntervalFn, *const c_void),
+}
+
+// Changes vsync on a context that already exists. glutin only lets us choose vsync when building
+// the context, so this loads the platform swap interval function itself. Like the context, this
+// has to stay on the render thread
+pub struct SwapControl {
+    swap_interval: Option<SwapInterval>,
+}
+
+impl SwapControl {
+    pub fn load(context: &Context<PossiblyCurrent>) -> SwapControl {
+        let load = |symbol: &str| {
+            let address = context.get_proc_address(symbol);
+            if address.is_null() { None } else { Some(address) }
+        };
+
+        let swap_interval = match unsafe { context.get_egl_display() } {
+            Some(display) => load("eglSwapInterval").map(|address| unsafe {
+                SwapInterval::Egl(std::mem::transmute::<*const c_void, EglSwapIntervalFn>(address), display)
+            }),
+            // glXGetProcAddress happily returns addresses for functions the driver doesn't have, so
+            // the order matters. MESA is the only GLX variant that reliably allows turning vsync off
+            None => {
+                let to_fn = |address| unsafe { std::mem::transmute::<*const c_void, SwapIntervalFn>(address) };
+                if cfg!(windows) {
+                    load("wglSwapIntervalEXT").map(|address| SwapInterval::Wgl(to_fn(address)))
+                } else {
+                    load("glXSwapIntervalMESA")
+                        .or_else(|| load("glXSwapIntervalSGI"))
+                        .map(|address| SwapInterval::Glx(to_fn(address)))
+                }
+            },
+        };
+
+        SwapControl { swap_interval }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.swap_interval.is_some()
+    }
+
+    pub fn set_vsync(&self, vsync: bool) -> Result<(), DisplayError> {
+        let interval = vsync as i32;
+        let success = unsafe {
+            match self.swap_interval {
+                Some(SwapInterval::Wgl(swap_interval)) => swap_interval(interval) != 0,
+                Some(SwapInterval::Glx(swap_interval)) => swap_interval(interval) == 0,
+                Some(SwapInterval::Egl(swap_interval, display)) => swap_interval(display, interval) != 0,
+                None => return Err(DisplayError::SwapIntervalUnsupported),
+            }
+        };
+
+        if success {
+            Ok(())
+        } else {
+            Err(DisplayError::SwapIntervalFailed)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DisplayError {
+    NoSuchMonitor(usize),
+    NoVideoModes,
+    SwapIntervalUnsupported,
+    SwapIntervalFailed,
+}
+
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayError::NoSuchMonitor(index) => write!(f, "No monitor with index {}", index),
+            DisplayError::NoVideoModes => write!(f, "Monitor reports no video modes for exclusive fullscreen"),
+            DisplayError::SwapIntervalUnsupported => write!(f, "Changing vsync at runtime is not supported by this driver"),
+            DisplayError::SwapIntervalFailed => write!(f, "Driver refused to change the swap interval"),
+        }
+    }
+}
+
+impl std::error::Error for DisplayError { }
@@ -0,0 +1,228 @@
+use std::{error, ffi, fmt, io};
+
+use gl::types::GLenum;
+
+use crate::config::ConfigError;
+use crate::display::DisplayError;
+use crate::input::bindings::BindingsError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Everything that can go wrong in the renderer, so `?` works across modules. Errors from other
+// crates are kept as the source, together with what we were doing when they happened
+#[derive(Debug)]
+pub enum Error {
+    Io { path: String, source: io::Error },
+    // Decoding or encoding an image file
+    Image { path: String, source: image::ImageError },
+    Shader(ShaderError),
+    Program(ProgramError),
+    // Invalid data handed to a vertex or index buffer
+    Buffer(String),
+    Texture(String),
+    Gl(GlError),
+    // Creating or making an OpenGL context current
+    Context(String),
+    // Reading or writing a GL call trace
+    Trace { path: String, message: String },
+    Config(ConfigError),
+    Bindings(BindingsError),
+    Display(DisplayError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, .. } => write!(f, "Failed to access {}", path),
+            Error::Image { path, .. } => write!(f, "Failed to process image {}", path),
+            Error::Shader(_) => write!(f, "Shader error"),
+            Error::Program(_) => write!(f, "Shader program error"),
+            Error::Buffer(message) => write!(f, "Buffer error: {}", message),
+            Error::Texture(message) => write!(f, "Texture error: {}", message),
+            Error::Gl(e) => e.fmt(f),
+            Error::Context(message) => write!(f, "OpenGL context error: {}", message),
+            Error::Trace { path, message } => write!(f, "Invalid trace {}: {}", path, message),
+            Error::Config(e) => e.fmt(f),
+            Error::Bindings(e) => e.fmt(f),
+            Error::Display(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            Error::Shader(e) => Some(e),
+            Error::Program(e) => Some(e),
+            // These show their own message, so their source is the one below it
+            Error::Config(e) => e.source(),
+            Error::Bindings(e) => e.source(),
+            Error::Display(e) => e.source(),
+            Error::Buffer(_) | Error::Texture(_) | Error::Gl(_) | Error::Context(_) | Error::Trace { .. } => None,
+        }
+    }
+}
+
+impl From<ShaderError> for Error {
+    fn from(e: ShaderError) -> Error {
+        Error::Shader(e)
+    }
+}
+
+impl From<ProgramError> for Error {
+    fn from(e: ProgramError) -> Error {
+        Error::Program(e)
+    }
+}
+
+impl From<GlError> for Error {
+    fn from(e: GlError) -> Error {
+        Error::Gl(e)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Error {
+        Error::Config(e)
+    }
+}
+
+impl From<BindingsError> for Error {
+    fn from(e: BindingsError) -> Error {
+        Error::Bindings(e)
+    }
+}
+
+impl From<DisplayError> for Error {
+    fn from(e: DisplayError) -> Error {
+        Error::Display(e)
+    }
+}
+
+// Attach the path that was being read or written to an io or image error, like
+// `fs::read_to_string(path).context(path.display())?`
+pub trait Context<T> {
+    fn context<P: fmt::Display>(self, path: P) -> Result<T>;
+}
+
+impl<T> Context<T> for std::result::Result<T, io::Error> {
+    fn context<P: fmt::Display>(self, path: P) -> Result<T> {
+        self.map_err(|source| Error::Io { path: path.to_string(), source })
+    }
+}
+
+impl<T> Context<T> for std::result::Result<T, image::ImageError> {
+    fn context<P: fmt::Display>(self, path: P) -> Result<T> {
+        self.map_err(|source| Error::Image { path: path.to_string(), source })
+    }
+}
+
+// An error and its sources on one line, like "Failed to read config file a.toml: No such file"
+pub fn chain(error: &dyn error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    // The shader type is decided by the file extension
+    UnknownType(String),
+    // The source contains a nul byte
+    Nul(ffi::NulError),
+    Compile { name: String, log: String },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::UnknownType(path) => write!(f, "Can't tell the shader type of {} from its extension", path),
+            ShaderError::Nul(_) => write!(f, "Shader source contains a nul byte"),
+            ShaderError::Compile { name, log } => write!(f, "Failed to compile {}:\n{}", name, log),
+        }
+    }
+}
+
+impl error::Error for ShaderError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ShaderError::Nul(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProgramError {
+    Link { log: String },
+    UniformNotFound(String),
+    // The uniform name contains a nul byte
+    Nul(ffi::NulError),
+    Uniform { name: String, source: GlError },
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgramError::Link { log } => write!(f, "Failed to link program:\n{}", log),
+            ProgramError::UniformNotFound(name) => write!(f, "Uniform \"{}\" not found, it may have been optimized out", name),
+            ProgramError::Nul(_) => write!(f, "Uniform name contains a nul byte"),
+            ProgramError::Uniform { name, .. } => write!(f, "Failed to access uniform \"{}\"", name),
+        }
+    }
+}
+
+impl error::Error for ProgramError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ProgramError::Nul(e) => Some(e),
+            ProgramError::Uniform { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// An error code from glGetError, and the call that raised it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlError {
+    pub code: GLenum,
+    pub operation: &'static str,
+}
+
+impl GlError {
+    // Turn the oldest pending GL error into a GlError, if there is one
+    pub fn check(operation: &'static str) -> std::result::Result<(), GlError> {
+        match unsafe { gl::GetError() } {
+            gl::NO_ERROR => Ok(()),
+            code => Err(GlError { code, operation }),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.code {
+            gl::INVALID_ENUM => "GL_INVALID_ENUM",
+            gl::INVALID_VALUE => "GL_INVALID_VALUE",
+            gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+            gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+            gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+            gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+            gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+            _ => "unknown GL error",
+        }
+    }
+}
+
+impl fmt::Display for GlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (0x{:04X}) in {}", self.name(), self.code, self.operation)
+    }
+}
+
+impl error::Error for GlError { }
@@ -0,0 +1,26 @@
+use crate::crash::CrashReport;
+use crate::input::event::InputEvent;
+
+// Everything the event loop forwards to the render thread
+#[derive(Clone, Copy, Debug)]
+pub enum RenderEvent {
+    Input(InputEvent),
+    // The window was resized, maximized or moved to a monitor with a different scale factor.
+    // Size is in physical pixels, and is zero while the window is minimized on some platforms
+    Resized { width: u32, height: u32, scale_factor: f64 },
+    // The window is closing. The render thread finishes its frame, shuts the app down and exits
+    Shutdown,
+}
+
+// Sent from other threads to wake up the event loop
+#[derive(Clone, Debug)]
+pub enum RunnerEvent {
+    // The render thread has exited and released its resources, so the process can end.
+    // Carries the reason if it died from a panic or an error
+    RenderThreadStopped { crash: Option<Box<CrashReport>> },
+    // Window changes requested by the render thread. winit windows may only be changed from the
+    // thread running the event loop, so the render thread asks for them instead
+    CycleWindowMode,
+    SetCursorGrab(bool),
+    SetTitle(String),
+}
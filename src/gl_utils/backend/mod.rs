@@ -0,0 +1,254 @@
+// Everything in gl_utils that creates, binds or deletes GL objects goes through a GlBackend
+// instead of calling the gl functions directly. Normally that is NativeGl, which just forwards
+// to the driver, but a RecordingBackend can be installed instead to test the logic around the
+// calls without a context or a GPU, and a TracingBackend records the calls on their way to the
+// driver.
+//
+// Objects remember the backend that was current when they were created and use it for their
+// whole life, so a Triangle created under a RecordingBackend is also deleted through it.
+pub mod native;
+pub mod recording;
+pub mod tracing;
+
+use std::{cell::RefCell, ffi::{c_void, CStr}, rc::Rc};
+
+use gl::types::{GLbitfield, GLenum, GLint, GLsizei, GLuint, GLDEBUGPROC};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GlError;
+use super::shaders::uniform::Uniform;
+
+pub use native::NativeGl;
+pub use recording::RecordingBackend;
+pub use tracing::TracingBackend;
+
+// A sync object, by its address. GL looks sync objects up before using them, so a deleted or
+// made up one is an error rather than a dangling pointer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fence(pub usize);
+
+// A recorded call, with everything needed to make it again. Only calls that create, change or
+// delete something are recorded, queries like glGetError aren't
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GlCall {
+    Enable(GLenum),
+    Disable(GLenum),
+    BlendFunc { source: GLenum, destination: GLenum },
+    PolygonMode { face: GLenum, mode: GLenum },
+    Viewport { x: GLint, y: GLint, width: GLsizei, height: GLsizei },
+    ClearColor([f32; 4]),
+    Clear(GLbitfield),
+    Finish,
+    GenVertexArrays(Vec<GLuint>),
+    DeleteVertexArrays(Vec<GLuint>),
+    BindVertexArray(GLuint),
+    EnableVertexAttribArray(GLuint),
+    VertexAttribPointer { index: GLuint, components: GLint, kind: GLenum, normalized: bool, stride: GLsizei, offset: usize },
+    GenBuffers(Vec<GLuint>),
+    DeleteBuffers(Vec<GLuint>),
+    BindBuffer { target: GLenum, id: GLuint },
+    BufferData { target: GLenum, data: Vec<u8>, usage: GLenum },
+    AllocateBuffer { target: GLenum, size: usize, usage: GLenum },
+    GenFramebuffers(Vec<GLuint>),
+    DeleteFramebuffers(Vec<GLuint>),
+    BindFramebuffer { target: GLenum, id: GLuint },
+    FramebufferRenderbuffer { target: GLenum, attachment: GLenum, renderbuffer: GLuint },
+    BlitFramebuffer { source: [GLint; 4], destination: [GLint; 4], mask: GLbitfield, filter: GLenum },
+    GenRenderbuffers(Vec<GLuint>),
+    DeleteRenderbuffers(Vec<GLuint>),
+    BindRenderbuffer(GLuint),
+    RenderbufferStorageMultisample { samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei },
+    PixelStore { parameter: GLenum, value: GLint },
+    // Reading into client memory only changes the pixels we got back, which aren't recorded
+    ReadPixels { x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum },
+    // Reading into the bound pixel pack buffer, at an offset in bytes
+    ReadPixelsToBuffer { x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, offset: usize },
+    FenceSync(usize),
+    DeleteSync(usize),
+    // Whether a callback was installed or removed. The callback itself can't be recorded
+    DebugMessageCallback(bool),
+    CreateShader { kind: GLenum, id: GLuint },
+    ShaderSource { shader: GLuint, source: String },
+    CompileShader(GLuint),
+    DeleteShader(GLuint),
+    CreateProgram(GLuint),
+    AttachShader { program: GLuint, shader: GLuint },
+    LinkProgram(GLuint),
+    UseProgram(GLuint),
+    DeleteProgram(GLuint),
+    // Only traces record this query, replaying needs it to translate locations
+    GetUniformLocation { program: GLuint, name: String, location: GLint },
+    // The program is the one in use when the uniform was set
+    Uniform { program: GLuint, location: GLint, value: Uniform },
+    GenQueries(Vec<GLuint>),
+    DeleteQueries(Vec<GLuint>),
+    QueryCounter { id: GLuint, target: GLenum },
+    ObjectLabel { identifier: GLenum, id: GLuint, label: String },
+    PushDebugGroup(String),
+    PopDebugGroup,
+    DrawElements { mode: GLenum, count: GLsizei, kind: GLenum, offset: usize },
+}
+
+// A thin, safe layer over the GL functions gl_utils and the runner need. Arguments mirror the GL functions,
+// except that pointers and lengths are slices and strings
+pub trait GlBackend {
+    fn get_error(&self) -> GLenum;
+    fn get_integer(&self, parameter: GLenum) -> GLint;
+    fn get_integer_i(&self, parameter: GLenum, index: GLuint) -> GLint;
+    // None when the driver doesn't know the name
+    fn get_string(&self, name: GLenum) -> Option<String>;
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> Option<String>;
+
+    fn enable(&self, capability: GLenum);
+    fn disable(&self, capability: GLenum);
+    fn blend_func(&self, source: GLenum, destination: GLenum);
+    fn polygon_mode(&self, face: GLenum, mode: GLenum);
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32);
+    fn clear(&self, mask: GLbitfield);
+    // Blocks until everything submitted so far has finished on the GPU
+    fn finish(&self);
+
+    fn gen_vertex_arrays(&self, ids: &mut [GLuint]);
+    fn delete_vertex_arrays(&self, ids: &[GLuint]);
+    fn bind_vertex_array(&self, id: GLuint);
+    fn enable_vertex_attrib_array(&self, index: GLuint);
+    // The offset is in bytes into the bound array buffer. Panics if none is bound
+    fn vertex_attrib_pointer(&self, index: GLuint, components: GLint, kind: GLenum, normalized: bool, stride: GLsizei, offset: usize);
+
+    fn gen_buffers(&self, ids: &mut [GLuint]);
+    fn delete_buffers(&self, ids: &[GLuint]);
+    fn bind_buffer(&self, target: GLenum, id: GLuint);
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum);
+    // glBufferData without data, for buffers the GPU writes into. The contents are undefined
+    fn allocate_buffer(&self, target: GLenum, size: usize, usage: GLenum);
+    // Copies the contents of the bound buffer, starting at offset, into data
+    fn get_buffer_sub_data(&self, target: GLenum, offset: usize, data: &mut [u8]);
+
+    fn gen_framebuffers(&self, ids: &mut [GLuint]);
+    fn delete_framebuffers(&self, ids: &[GLuint]);
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint);
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum;
+    // Attaches a renderbuffer of the bound GL_RENDERBUFFER kind
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint);
+    // Rectangles are x0, y0, x1, y1 like the GL function takes them
+    fn blit_framebuffer(&self, source: [GLint; 4], destination: [GLint; 4], mask: GLbitfield, filter: GLenum);
+
+    fn gen_renderbuffers(&self, ids: &mut [GLuint]);
+    fn delete_renderbuffers(&self, ids: &[GLuint]);
+    fn bind_renderbuffer(&self, id: GLuint);
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei);
+
+    fn pixel_store(&self, parameter: GLenum, value: GLint);
+    // Reads from the bound read framebuffer into pixels, which has to fit the whole rectangle
+    #[allow(clippy::too_many_arguments)]
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, pixels: &mut [u8]);
+    // Like read_pixels, into the bound pixel pack buffer. Returns right away, the copy happens on the GPU.
+    // Panics if no pack buffer is bound
+    #[allow(clippy::too_many_arguments)]
+    fn read_pixels_to_buffer(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, offset: usize);
+
+    // A fence that is signaled once everything submitted before it has finished
+    fn fence_sync(&self) -> Fence;
+    // GL_ALREADY_SIGNALED, GL_CONDITION_SATISFIED, GL_TIMEOUT_EXPIRED or GL_WAIT_FAILED
+    fn client_wait_sync(&self, fence: Fence, flags: GLbitfield, timeout_ns: u64) -> GLenum;
+    fn delete_sync(&self, fence: Fence);
+
+    fn create_shader(&self, kind: GLenum) -> GLuint;
+    fn shader_source(&self, shader: GLuint, source: &CStr);
+    fn compile_shader(&self, shader: GLuint);
+    fn get_shader_iv(&self, shader: GLuint, parameter: GLenum) -> GLint;
+    fn shader_info_log(&self, shader: GLuint) -> String;
+    fn delete_shader(&self, shader: GLuint);
+
+    fn create_program(&self) -> GLuint;
+    fn attach_shader(&self, program: GLuint, shader: GLuint);
+    fn link_program(&self, program: GLuint);
+    fn get_program_iv(&self, program: GLuint, parameter: GLenum) -> GLint;
+    fn program_info_log(&self, program: GLuint) -> String;
+    fn use_program(&self, program: GLuint);
+    fn delete_program(&self, program: GLuint);
+    fn get_uniform_location(&self, program: GLuint, name: &CStr) -> GLint;
+    // Sets the uniform on the program in use
+    fn uniform(&self, location: GLint, value: Uniform);
+
+    fn gen_queries(&self, ids: &mut [GLuint]);
+    fn delete_queries(&self, ids: &[GLuint]);
+    // Records the GPU time into the query once everything before it has finished
+    fn query_counter(&self, id: GLuint, target: GLenum);
+    fn get_query_object_iv(&self, id: GLuint, parameter: GLenum) -> GLint;
+    // Blocks until the result is available, check QUERY_RESULT_AVAILABLE first
+    fn get_query_object_u64(&self, id: GLuint, parameter: GLenum) -> u64;
+
+    // Names for objects and groups of calls, shown by debuggers and in debug messages. Need
+    // KHR_debug, see gl_utils::debug for helpers that check
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str);
+    fn push_debug_group(&self, message: &str);
+    fn pop_debug_group(&self);
+    // Install or, with None, remove the debug message callback.
+    //
+    // Safety: the driver hands user_param to the callback, so it has to stay valid for as long
+    // as the callback is installed
+    #[allow(clippy::missing_safety_doc)]
+    unsafe fn debug_message_callback(&self, callback: GLDEBUGPROC, user_param: *const c_void);
+
+    // The offset is in bytes into the bound element buffer. Panics if none is bound
+    fn draw_elements(&self, mode: GLenum, count: GLsizei, kind: GLenum, offset: usize);
+
+    // Turn the oldest pending error into a GlError, like GlError::check
+    fn check_error(&self, operation: &'static str) -> Result<(), GlError> {
+        match self.get_error() {
+            gl::NO_ERROR => Ok(()),
+            code => Err(GlError { code, operation }),
+        }
+    }
+}
+
+// How many bytes glReadPixels writes for a rectangle, with rows padded to the pack alignment.
+// Formats with more or bigger components than listed here are taken as 4 components of 4 bytes
+pub fn pixel_data_size(width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, alignment: GLint) -> usize {
+    let components = match format {
+        gl::RED | gl::GREEN | gl::BLUE | gl::ALPHA | gl::DEPTH_COMPONENT | gl::STENCIL_INDEX => 1,
+        gl::RG => 2,
+        gl::RGB | gl::BGR => 3,
+        _ => 4,
+    };
+    let size = match kind {
+        gl::UNSIGNED_BYTE | gl::BYTE => 1,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2,
+        _ => 4,
+    };
+    let alignment = alignment.max(1) as usize;
+    let row = components * size * width.max(0) as usize;
+    row.div_ceil(alignment) * alignment * height.max(0) as usize
+}
+
+thread_local! {
+    // GL contexts are current per thread, so backends are too
+    static CURRENT: RefCell<Rc<dyn GlBackend>> = RefCell::new(Rc::new(NativeGl));
+}
+
+// The backend new objects on this thread are created with
+pub fn current() -> Rc<dyn GlBackend> {
+    CURRENT.with(|current| Rc::clone(&current.borrow()))
+}
+
+// Make the backend current on this thread until the returned guard is dropped
+#[must_use = "the previous backend is restored when the guard is dropped"]
+pub fn install(backend: Rc<dyn GlBackend>) -> BackendGuard {
+    let previous = CURRENT.with(|current| current.replace(backend));
+    BackendGuard { previous: Some(previous) }
+}
+
+pub struct BackendGuard {
+    previous: Option<Rc<dyn GlBackend>>,
+}
+
+impl Drop for BackendGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            CURRENT.with(|current| current.replace(previous));
+        }
+    }
+}
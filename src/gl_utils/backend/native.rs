@@ -0,0 +1,325 @@
+use std::{ffi::{c_void, CStr}, ptr};
+
+use gl::types::{GLbitfield, GLchar, GLenum, GLint, GLsizei, GLsync, GLubyte, GLuint, GLDEBUGPROC};
+
+use crate::gl_utils::shaders::uniform::Uniform;
+use super::{pixel_data_size, Fence, GlBackend};
+
+// Info logs longer than this are cut off
+const INFO_LOG_LENGTH: usize = 512;
+
+// Forwards every call to the driver through the loaded gl functions. Needs a current context
+pub struct NativeGl;
+
+impl GlBackend for NativeGl {
+    fn get_error(&self) -> GLenum {
+        unsafe { gl::GetError() }
+    }
+
+    fn get_integer(&self, parameter: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetIntegerv(parameter, &mut value) };
+        value
+    }
+
+    fn get_integer_i(&self, parameter: GLenum, index: GLuint) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetIntegeri_v(parameter, index, &mut value) };
+        value
+    }
+
+    fn get_string(&self, name: GLenum) -> Option<String> {
+        unsafe { owned_string(gl::GetString(name)) }
+    }
+
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> Option<String> {
+        unsafe { owned_string(gl::GetStringi(name, index)) }
+    }
+
+    fn enable(&self, capability: GLenum) {
+        unsafe { gl::Enable(capability) }
+    }
+
+    fn disable(&self, capability: GLenum) {
+        unsafe { gl::Disable(capability) }
+    }
+
+    fn blend_func(&self, source: GLenum, destination: GLenum) {
+        unsafe { gl::BlendFunc(source, destination) }
+    }
+
+    fn polygon_mode(&self, face: GLenum, mode: GLenum) {
+        unsafe { gl::PolygonMode(face, mode) }
+    }
+
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        unsafe { gl::Viewport(x, y, width, height) }
+    }
+
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        unsafe { gl::ClearColor(r, g, b, a) }
+    }
+
+    fn clear(&self, mask: GLbitfield) {
+        unsafe { gl::Clear(mask) }
+    }
+
+    fn finish(&self) {
+        unsafe { gl::Finish() }
+    }
+
+    fn gen_vertex_arrays(&self, ids: &mut [GLuint]) {
+        unsafe { gl::GenVertexArrays(ids.len() as GLsizei, ids.as_mut_ptr()) }
+    }
+
+    fn delete_vertex_arrays(&self, ids: &[GLuint]) {
+        unsafe { gl::DeleteVertexArrays(ids.len() as GLsizei, ids.as_ptr()) }
+    }
+
+    fn bind_vertex_array(&self, id: GLuint) {
+        unsafe { gl::BindVertexArray(id) }
+    }
+
+    fn enable_vertex_attrib_array(&self, index: GLuint) {
+        unsafe { gl::EnableVertexAttribArray(index) }
+    }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, components: GLint, kind: GLenum, normalized: bool, stride: GLsizei, offset: usize) {
+        // Without an array buffer bound, GL would take the offset as a pointer into our memory
+        assert_ne!(self.get_integer(gl::ARRAY_BUFFER_BINDING), 0, "vertex_attrib_pointer without an array buffer bound");
+        let normalized = if normalized { gl::TRUE } else { gl::FALSE };
+        unsafe { gl::VertexAttribPointer(index, components, kind, normalized, stride, offset as *const c_void) }
+    }
+
+    fn gen_buffers(&self, ids: &mut [GLuint]) {
+        unsafe { gl::GenBuffers(ids.len() as GLsizei, ids.as_mut_ptr()) }
+    }
+
+    fn delete_buffers(&self, ids: &[GLuint]) {
+        unsafe { gl::DeleteBuffers(ids.len() as GLsizei, ids.as_ptr()) }
+    }
+
+    fn bind_buffer(&self, target: GLenum, id: GLuint) {
+        unsafe { gl::BindBuffer(target, id) }
+    }
+
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum) {
+        unsafe { gl::BufferData(target, data.len() as isize, data.as_ptr() as *const c_void, usage) }
+    }
+
+    fn allocate_buffer(&self, target: GLenum, size: usize, usage: GLenum) {
+        unsafe { gl::BufferData(target, size as isize, ptr::null(), usage) }
+    }
+
+    fn get_buffer_sub_data(&self, target: GLenum, offset: usize, data: &mut [u8]) {
+        unsafe { gl::GetBufferSubData(target, offset as isize, data.len() as isize, data.as_mut_ptr() as *mut c_void) }
+    }
+
+    fn gen_framebuffers(&self, ids: &mut [GLuint]) {
+        unsafe { gl::GenFramebuffers(ids.len() as GLsizei, ids.as_mut_ptr()) }
+    }
+
+    fn delete_framebuffers(&self, ids: &[GLuint]) {
+        unsafe { gl::DeleteFramebuffers(ids.len() as GLsizei, ids.as_ptr()) }
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint) {
+        unsafe { gl::BindFramebuffer(target, id) }
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        unsafe { gl::CheckFramebufferStatus(target) }
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint) {
+        unsafe { gl::FramebufferRenderbuffer(target, attachment, gl::RENDERBUFFER, renderbuffer) }
+    }
+
+    fn blit_framebuffer(&self, source: [GLint; 4], destination: [GLint; 4], mask: GLbitfield, filter: GLenum) {
+        let ([sx0, sy0, sx1, sy1], [dx0, dy0, dx1, dy1]) = (source, destination);
+        unsafe { gl::BlitFramebuffer(sx0, sy0, sx1, sy1, dx0, dy0, dx1, dy1, mask, filter) }
+    }
+
+    fn gen_renderbuffers(&self, ids: &mut [GLuint]) {
+        unsafe { gl::GenRenderbuffers(ids.len() as GLsizei, ids.as_mut_ptr()) }
+    }
+
+    fn delete_renderbuffers(&self, ids: &[GLuint]) {
+        unsafe { gl::DeleteRenderbuffers(ids.len() as GLsizei, ids.as_ptr()) }
+    }
+
+    fn bind_renderbuffer(&self, id: GLuint) {
+        unsafe { gl::BindRenderbuffer(gl::RENDERBUFFER, id) }
+    }
+
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei) {
+        unsafe { gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, format, width, height) }
+    }
+
+    fn pixel_store(&self, parameter: GLenum, value: GLint) {
+        unsafe { gl::PixelStorei(parameter, value) }
+    }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, pixels: &mut [u8]) {
+        // The driver writes as much as the rectangle needs, so make sure pixels can take it. With a
+        // pack buffer bound, the pointer would be taken as an offset into it instead
+        assert_eq!(self.get_integer(gl::PIXEL_PACK_BUFFER_BINDING), 0, "read_pixels with a pack buffer bound");
+        let size = pixel_data_size(width, height, format, kind, self.get_integer(gl::PACK_ALIGNMENT));
+        assert!(pixels.len() >= size, "{} bytes are too few for a {}x{} read", pixels.len(), width, height);
+        unsafe { gl::ReadPixels(x, y, width, height, format, kind, pixels.as_mut_ptr() as *mut c_void) }
+    }
+
+    fn read_pixels_to_buffer(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, offset: usize) {
+        // The opposite of read_pixels, without a pack buffer the offset would be written to as a pointer
+        assert_ne!(self.get_integer(gl::PIXEL_PACK_BUFFER_BINDING), 0, "read_pixels_to_buffer without a pack buffer bound");
+        unsafe { gl::ReadPixels(x, y, width, height, format, kind, offset as *mut c_void) }
+    }
+
+    fn fence_sync(&self) -> Fence {
+        Fence(unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) } as usize)
+    }
+
+    fn client_wait_sync(&self, fence: Fence, flags: GLbitfield, timeout_ns: u64) -> GLenum {
+        unsafe { gl::ClientWaitSync(fence.0 as GLsync, flags, timeout_ns) }
+    }
+
+    fn delete_sync(&self, fence: Fence) {
+        unsafe { gl::DeleteSync(fence.0 as GLsync) }
+    }
+
+    fn create_shader(&self, kind: GLenum) -> GLuint {
+        unsafe { gl::CreateShader(kind) }
+    }
+
+    fn shader_source(&self, shader: GLuint, source: &CStr) {
+        unsafe { gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null()) }
+    }
+
+    fn compile_shader(&self, shader: GLuint) {
+        unsafe { gl::CompileShader(shader) }
+    }
+
+    fn get_shader_iv(&self, shader: GLuint, parameter: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetShaderiv(shader, parameter, &mut value) };
+        value
+    }
+
+    fn shader_info_log(&self, shader: GLuint) -> String {
+        info_log(|length, written, log| unsafe { gl::GetShaderInfoLog(shader, length, written, log) })
+    }
+
+    fn delete_shader(&self, shader: GLuint) {
+        unsafe { gl::DeleteShader(shader) }
+    }
+
+    fn create_program(&self) -> GLuint {
+        unsafe { gl::CreateProgram() }
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) {
+        unsafe { gl::AttachShader(program, shader) }
+    }
+
+    fn link_program(&self, program: GLuint) {
+        unsafe { gl::LinkProgram(program) }
+    }
+
+    fn get_program_iv(&self, program: GLuint, parameter: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetProgramiv(program, parameter, &mut value) };
+        value
+    }
+
+    fn program_info_log(&self, program: GLuint) -> String {
+        info_log(|length, written, log| unsafe { gl::GetProgramInfoLog(program, length, written, log) })
+    }
+
+    fn use_program(&self, program: GLuint) {
+        unsafe { gl::UseProgram(program) }
+    }
+
+    fn delete_program(&self, program: GLuint) {
+        unsafe { gl::DeleteProgram(program) }
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &CStr) -> GLint {
+        unsafe { gl::GetUniformLocation(program, name.as_ptr()) }
+    }
+
+    fn uniform(&self, location: GLint, value: Uniform) {
+        unsafe {
+            match value {
+                Uniform::Float(v) => gl::Uniform1f(location, v),
+                Uniform::Int(v) => gl::Uniform1i(location, v),
+                Uniform::UInt(v) => gl::Uniform1ui(location, v),
+                Uniform::Vec2(v) => gl::Uniform2fv(location, 1, v.as_ptr()),
+                Uniform::Vec3(v) => gl::Uniform3fv(location, 1, v.as_ptr()),
+                Uniform::Vec4(v) => gl::Uniform4fv(location, 1, v.as_ptr()),
+                Uniform::Mat3(v) => gl::UniformMatrix3fv(location, 1, gl::FALSE, v.as_ptr()),
+                Uniform::Mat4(v) => gl::UniformMatrix4fv(location, 1, gl::FALSE, v.as_ptr()),
+            }
+        }
+    }
+
+    fn gen_queries(&self, ids: &mut [GLuint]) {
+        unsafe { gl::GenQueries(ids.len() as GLsizei, ids.as_mut_ptr()) }
+    }
+
+    fn delete_queries(&self, ids: &[GLuint]) {
+        unsafe { gl::DeleteQueries(ids.len() as GLsizei, ids.as_ptr()) }
+    }
+
+    fn query_counter(&self, id: GLuint, target: GLenum) {
+        unsafe { gl::QueryCounter(id, target) }
+    }
+
+    fn get_query_object_iv(&self, id: GLuint, parameter: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetQueryObjectiv(id, parameter, &mut value) };
+        value
+    }
+
+    fn get_query_object_u64(&self, id: GLuint, parameter: GLenum) -> u64 {
+        let mut value = 0;
+        unsafe { gl::GetQueryObjectui64v(id, parameter, &mut value) };
+        value
+    }
+
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str) {
+        unsafe { gl::ObjectLabel(identifier, id, label.len() as GLsizei, label.as_ptr() as *const GLchar) }
+    }
+
+    fn push_debug_group(&self, message: &str) {
+        unsafe { gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, message.len() as GLsizei, message.as_ptr() as *const GLchar) }
+    }
+
+    fn pop_debug_group(&self) {
+        unsafe { gl::PopDebugGroup() }
+    }
+
+    unsafe fn debug_message_callback(&self, callback: GLDEBUGPROC, user_param: *const c_void) {
+        gl::DebugMessageCallback(callback, user_param)
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: GLsizei, kind: GLenum, offset: usize) {
+        // Indices are read from the offset as a pointer if the vertex array has no index buffer
+        assert_ne!(self.get_integer(gl::ELEMENT_ARRAY_BUFFER_BINDING), 0, "draw_elements without an index buffer bound");
+        unsafe { gl::DrawElements(mode, count, kind, offset as *const c_void) }
+    }
+}
+
+unsafe fn owned_string(string: *const GLubyte) -> Option<String> {
+    if string.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(string as *const _).to_string_lossy().into_owned())
+    }
+}
+
+fn info_log(read: impl FnOnce(GLsizei, *mut GLsizei, *mut GLchar)) -> String {
+    let mut log = vec![0u8; INFO_LOG_LENGTH];
+    let mut length = 0;
+    read(INFO_LOG_LENGTH as GLsizei, &mut length, log.as_mut_ptr() as *mut GLchar);
+    String::from_utf8_lossy(&log[..length.max(0) as usize]).into_owned()
+}
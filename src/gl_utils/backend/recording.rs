@@ -0,0 +1,484 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    ffi::{c_void, CStr},
+};
+
+use gl::types::{GLbitfield, GLenum, GLint, GLsizei, GLuint, GLDEBUGPROC};
+
+use crate::gl_utils::shaders::uniform::Uniform;
+use super::{Fence, GlBackend, GlCall};
+
+// A fake backend for tests. It records every call, hands out increasing ids starting at 1 and
+// keeps just enough state to answer the queries gl_utils makes. By default every shader
+// compiles, every program links and every uniform exists
+#[derive(Default)]
+pub struct RecordingBackend {
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    calls: Vec<GlCall>,
+    last_id: GLuint,
+    // Objects that were created and not deleted yet
+    live: HashSet<GLuint>,
+    errors: VecDeque<GLenum>,
+    program_in_use: GLuint,
+    draw_framebuffer: GLuint,
+    read_framebuffer: GLuint,
+    pixel_pack_buffer: GLuint,
+    shader_sources: HashMap<GLuint, String>,
+    // (program, name) to location, in the order they were asked for
+    uniform_locations: HashMap<(GLuint, String), GLint>,
+
+    // Failures to simulate
+    compile_failures: Vec<(String, String)>,
+    link_failure: Option<String>,
+    missing_uniforms: HashSet<String>,
+
+    // Timer queries get the simulated GPU clock when they are written, in nanoseconds
+    gpu_time: u64,
+    query_results: HashMap<GLuint, u64>,
+    hold_query_results: bool,
+}
+
+impl State {
+    fn create(&mut self) -> GLuint {
+        self.last_id += 1;
+        self.live.insert(self.last_id);
+        self.last_id
+    }
+
+    fn compile_log(&self, shader: GLuint) -> Option<&str> {
+        let source = self.shader_sources.get(&shader)?;
+        self.compile_failures.iter()
+            .find(|(pattern, _)| source.contains(pattern.as_str()))
+            .map(|(_, log)| log.as_str())
+    }
+}
+
+impl RecordingBackend {
+    pub fn new() -> RecordingBackend {
+        RecordingBackend::default()
+    }
+
+    pub fn calls(&self) -> Vec<GlCall> {
+        self.state.borrow().calls.clone()
+    }
+
+    // The calls recorded so far, and start over with an empty list
+    pub fn take_calls(&self) -> Vec<GlCall> {
+        std::mem::take(&mut self.state.borrow_mut().calls)
+    }
+
+    // How many objects were created and never deleted
+    pub fn live_objects(&self) -> usize {
+        self.state.borrow().live.len()
+    }
+
+    // The location handed out for a uniform, if the program asked for it
+    pub fn uniform_location(&self, program: GLuint, name: &str) -> Option<GLint> {
+        self.state.borrow().uniform_locations.get(&(program, name.to_string())).copied()
+    }
+
+    // Have glGetError return this code next
+    pub fn queue_error(&self, code: GLenum) {
+        self.state.borrow_mut().errors.push_back(code);
+    }
+
+    // Shaders with the pattern somewhere in their source fail to compile with this log
+    pub fn fail_compile(&self, pattern: &str, log: &str) {
+        self.state.borrow_mut().compile_failures.push((pattern.to_string(), log.to_string()));
+    }
+
+    // Every program fails to link with this log
+    pub fn fail_link(&self, log: &str) {
+        self.state.borrow_mut().link_failure = Some(log.to_string());
+    }
+
+    // glGetUniformLocation returns -1 for this name
+    pub fn remove_uniform(&self, name: &str) {
+        self.state.borrow_mut().missing_uniforms.insert(name.to_string());
+    }
+
+    // Move the simulated GPU clock forward, as if the GPU spent this long on the calls so far
+    pub fn advance_gpu_time(&self, nanoseconds: u64) {
+        self.state.borrow_mut().gpu_time += nanoseconds;
+    }
+
+    // While held, query results are never available, like on a GPU that is frames behind
+    pub fn hold_query_results(&self, hold: bool) {
+        self.state.borrow_mut().hold_query_results = hold;
+    }
+
+    fn record(&self, call: GlCall) {
+        self.state.borrow_mut().calls.push(call);
+    }
+
+    fn delete(&self, ids: &[GLuint]) {
+        let mut state = self.state.borrow_mut();
+        for id in ids {
+            state.live.remove(id);
+        }
+    }
+}
+
+impl GlBackend for RecordingBackend {
+    fn get_error(&self) -> GLenum {
+        self.state.borrow_mut().errors.pop_front().unwrap_or(gl::NO_ERROR)
+    }
+
+    fn get_integer(&self, parameter: GLenum) -> GLint {
+        let state = self.state.borrow();
+        match parameter {
+            gl::CURRENT_PROGRAM => state.program_in_use as GLint,
+            gl::DRAW_FRAMEBUFFER_BINDING => state.draw_framebuffer as GLint,
+            gl::READ_FRAMEBUFFER_BINDING => state.read_framebuffer as GLint,
+            gl::PIXEL_PACK_BUFFER_BINDING => state.pixel_pack_buffer as GLint,
+            _ => 0,
+        }
+    }
+
+    fn get_integer_i(&self, _parameter: GLenum, _index: GLuint) -> GLint {
+        0
+    }
+
+    fn get_string(&self, _name: GLenum) -> Option<String> {
+        None
+    }
+
+    fn get_string_i(&self, _name: GLenum, _index: GLuint) -> Option<String> {
+        None
+    }
+
+    fn enable(&self, capability: GLenum) {
+        self.record(GlCall::Enable(capability));
+    }
+
+    fn disable(&self, capability: GLenum) {
+        self.record(GlCall::Disable(capability));
+    }
+
+    fn blend_func(&self, source: GLenum, destination: GLenum) {
+        self.record(GlCall::BlendFunc { source, destination });
+    }
+
+    fn polygon_mode(&self, face: GLenum, mode: GLenum) {
+        self.record(GlCall::PolygonMode { face, mode });
+    }
+
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        self.record(GlCall::Viewport { x, y, width, height });
+    }
+
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.record(GlCall::ClearColor([r, g, b, a]));
+    }
+
+    fn clear(&self, mask: GLbitfield) {
+        self.record(GlCall::Clear(mask));
+    }
+
+    fn finish(&self) {
+        self.record(GlCall::Finish);
+    }
+
+    fn gen_vertex_arrays(&self, ids: &mut [GLuint]) {
+        let mut state = self.state.borrow_mut();
+        ids.iter_mut().for_each(|id| *id = state.create());
+        state.calls.push(GlCall::GenVertexArrays(ids.to_vec()));
+    }
+
+    fn delete_vertex_arrays(&self, ids: &[GLuint]) {
+        self.delete(ids);
+        self.record(GlCall::DeleteVertexArrays(ids.to_vec()));
+    }
+
+    fn bind_vertex_array(&self, id: GLuint) {
+        self.record(GlCall::BindVertexArray(id));
+    }
+
+    fn enable_vertex_attrib_array(&self, index: GLuint) {
+        self.record(GlCall::EnableVertexAttribArray(index));
+    }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, components: GLint, kind: GLenum, normalized: bool, stride: GLsizei, offset: usize) {
+        self.record(GlCall::VertexAttribPointer { index, components, kind, normalized, stride, offset });
+    }
+
+    fn gen_buffers(&self, ids: &mut [GLuint]) {
+        let mut state = self.state.borrow_mut();
+        ids.iter_mut().for_each(|id| *id = state.create());
+        state.calls.push(GlCall::GenBuffers(ids.to_vec()));
+    }
+
+    fn delete_buffers(&self, ids: &[GLuint]) {
+        self.delete(ids);
+        self.record(GlCall::DeleteBuffers(ids.to_vec()));
+    }
+
+    fn bind_buffer(&self, target: GLenum, id: GLuint) {
+        if target == gl::PIXEL_PACK_BUFFER {
+            self.state.borrow_mut().pixel_pack_buffer = id;
+        }
+        self.record(GlCall::BindBuffer { target, id });
+    }
+
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum) {
+        self.record(GlCall::BufferData { target, data: data.to_vec(), usage });
+    }
+
+    fn allocate_buffer(&self, target: GLenum, size: usize, usage: GLenum) {
+        self.record(GlCall::AllocateBuffer { target, size, usage });
+    }
+
+    // Buffers don't keep their contents, so this reads zeros
+    fn get_buffer_sub_data(&self, _target: GLenum, _offset: usize, data: &mut [u8]) {
+        data.iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    fn gen_framebuffers(&self, ids: &mut [GLuint]) {
+        let mut state = self.state.borrow_mut();
+        ids.iter_mut().for_each(|id| *id = state.create());
+        state.calls.push(GlCall::GenFramebuffers(ids.to_vec()));
+    }
+
+    fn delete_framebuffers(&self, ids: &[GLuint]) {
+        self.delete(ids);
+        self.record(GlCall::DeleteFramebuffers(ids.to_vec()));
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint) {
+        {
+            let mut state = self.state.borrow_mut();
+            if target != gl::READ_FRAMEBUFFER {
+                state.draw_framebuffer = id;
+            }
+            if target != gl::DRAW_FRAMEBUFFER {
+                state.read_framebuffer = id;
+            }
+        }
+        self.record(GlCall::BindFramebuffer { target, id });
+    }
+
+    fn check_framebuffer_status(&self, _target: GLenum) -> GLenum {
+        gl::FRAMEBUFFER_COMPLETE
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint) {
+        self.record(GlCall::FramebufferRenderbuffer { target, attachment, renderbuffer });
+    }
+
+    fn blit_framebuffer(&self, source: [GLint; 4], destination: [GLint; 4], mask: GLbitfield, filter: GLenum) {
+        self.record(GlCall::BlitFramebuffer { source, destination, mask, filter });
+    }
+
+    fn gen_renderbuffers(&self, ids: &mut [GLuint]) {
+        let mut state = self.state.borrow_mut();
+        ids.iter_mut().for_each(|id| *id = state.create());
+        state.calls.push(GlCall::GenRenderbuffers(ids.to_vec()));
+    }
+
+    fn delete_renderbuffers(&self, ids: &[GLuint]) {
+        self.delete(ids);
+        self.record(GlCall::DeleteRenderbuffers(ids.to_vec()));
+    }
+
+    fn bind_renderbuffer(&self, id: GLuint) {
+        self.record(GlCall::BindRenderbuffer(id));
+    }
+
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei) {
+        self.record(GlCall::RenderbufferStorageMultisample { samples, format, width, height });
+    }
+
+    fn pixel_store(&self, parameter: GLenum, value: GLint) {
+        self.record(GlCall::PixelStore { parameter, value });
+    }
+
+    // Nothing is ever drawn, so every pixel reads as zero
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, pixels: &mut [u8]) {
+        pixels.iter_mut().for_each(|byte| *byte = 0);
+        self.record(GlCall::ReadPixels { x, y, width, height, format, kind });
+    }
+
+    fn read_pixels_to_buffer(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, offset: usize) {
+        self.record(GlCall::ReadPixelsToBuffer { x, y, width, height, format, kind, offset });
+    }
+
+    // Fences are ids like any other object, and are signaled right away
+    fn fence_sync(&self) -> Fence {
+        let mut state = self.state.borrow_mut();
+        let id = state.create() as usize;
+        state.calls.push(GlCall::FenceSync(id));
+        Fence(id)
+    }
+
+    fn client_wait_sync(&self, fence: Fence, _flags: GLbitfield, _timeout_ns: u64) -> GLenum {
+        let live = self.state.borrow().live.contains(&(fence.0 as GLuint));
+        if live { gl::ALREADY_SIGNALED } else { gl::WAIT_FAILED }
+    }
+
+    fn delete_sync(&self, fence: Fence) {
+        self.delete(&[fence.0 as GLuint]);
+        self.record(GlCall::DeleteSync(fence.0));
+    }
+
+    fn create_shader(&self, kind: GLenum) -> GLuint {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.calls.push(GlCall::CreateShader { kind, id });
+        id
+    }
+
+    fn shader_source(&self, shader: GLuint, source: &CStr) {
+        let source = source.to_string_lossy().into_owned();
+        let mut state = self.state.borrow_mut();
+        state.shader_sources.insert(shader, source.clone());
+        state.calls.push(GlCall::ShaderSource { shader, source });
+    }
+
+    fn compile_shader(&self, shader: GLuint) {
+        self.record(GlCall::CompileShader(shader));
+    }
+
+    fn get_shader_iv(&self, shader: GLuint, parameter: GLenum) -> GLint {
+        match parameter {
+            gl::COMPILE_STATUS => {
+                let compiled = self.state.borrow().compile_log(shader).is_none();
+                GLint::from(if compiled { gl::TRUE } else { gl::FALSE })
+            },
+            _ => 0,
+        }
+    }
+
+    fn shader_info_log(&self, shader: GLuint) -> String {
+        self.state.borrow().compile_log(shader).unwrap_or_default().to_string()
+    }
+
+    fn delete_shader(&self, shader: GLuint) {
+        self.delete(&[shader]);
+        self.record(GlCall::DeleteShader(shader));
+    }
+
+    fn create_program(&self) -> GLuint {
+        let mut state = self.state.borrow_mut();
+        let id = state.create();
+        state.calls.push(GlCall::CreateProgram(id));
+        id
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) {
+        self.record(GlCall::AttachShader { program, shader });
+    }
+
+    fn link_program(&self, program: GLuint) {
+        self.record(GlCall::LinkProgram(program));
+    }
+
+    fn get_program_iv(&self, _program: GLuint, parameter: GLenum) -> GLint {
+        match parameter {
+            gl::LINK_STATUS => {
+                let linked = self.state.borrow().link_failure.is_none();
+                GLint::from(if linked { gl::TRUE } else { gl::FALSE })
+            },
+            _ => 0,
+        }
+    }
+
+    fn program_info_log(&self, _program: GLuint) -> String {
+        self.state.borrow().link_failure.clone().unwrap_or_default()
+    }
+
+    fn use_program(&self, program: GLuint) {
+        self.state.borrow_mut().program_in_use = program;
+        self.record(GlCall::UseProgram(program));
+    }
+
+    fn delete_program(&self, program: GLuint) {
+        self.delete(&[program]);
+        self.record(GlCall::DeleteProgram(program));
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &CStr) -> GLint {
+        let name = name.to_string_lossy().into_owned();
+        let mut state = self.state.borrow_mut();
+        if state.missing_uniforms.contains(&name) {
+            return -1;
+        }
+        let next = state.uniform_locations.keys().filter(|(p, _)| *p == program).count() as GLint;
+        *state.uniform_locations.entry((program, name)).or_insert(next)
+    }
+
+    fn uniform(&self, location: GLint, value: Uniform) {
+        let program = self.state.borrow().program_in_use;
+        self.record(GlCall::Uniform { program, location, value });
+    }
+
+    fn gen_queries(&self, ids: &mut [GLuint]) {
+        {
+            let mut state = self.state.borrow_mut();
+            for id in ids.iter_mut() {
+                *id = state.create();
+            }
+        }
+        self.record(GlCall::GenQueries(ids.to_vec()));
+    }
+
+    fn delete_queries(&self, ids: &[GLuint]) {
+        self.delete(ids);
+        {
+            let mut state = self.state.borrow_mut();
+            for id in ids {
+                state.query_results.remove(id);
+            }
+        }
+        self.record(GlCall::DeleteQueries(ids.to_vec()));
+    }
+
+    fn query_counter(&self, id: GLuint, target: GLenum) {
+        {
+            let mut state = self.state.borrow_mut();
+            let time = state.gpu_time;
+            state.query_results.insert(id, time);
+        }
+        self.record(GlCall::QueryCounter { id, target });
+    }
+
+    fn get_query_object_iv(&self, id: GLuint, parameter: GLenum) -> GLint {
+        let state = self.state.borrow();
+        match parameter {
+            gl::QUERY_RESULT_AVAILABLE => (state.query_results.contains_key(&id) && !state.hold_query_results) as GLint,
+            _ => 0,
+        }
+    }
+
+    fn get_query_object_u64(&self, id: GLuint, parameter: GLenum) -> u64 {
+        match parameter {
+            gl::QUERY_RESULT => self.state.borrow().query_results.get(&id).copied().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str) {
+        self.record(GlCall::ObjectLabel { identifier, id, label: label.to_string() });
+    }
+
+    fn push_debug_group(&self, message: &str) {
+        self.record(GlCall::PushDebugGroup(message.to_string()));
+    }
+
+    fn pop_debug_group(&self) {
+        self.record(GlCall::PopDebugGroup);
+    }
+
+    // The callback is never called, there are no messages
+    unsafe fn debug_message_callback(&self, callback: GLDEBUGPROC, _user_param: *const c_void) {
+        self.record(GlCall::DebugMessageCallback(callback.is_some()));
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: GLsizei, kind: GLenum, offset: usize) {
+        self.record(GlCall::DrawElements { mode, count, kind, offset });
+    }
+}
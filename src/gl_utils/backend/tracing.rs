@@ -0,0 +1,371 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_void, CStr},
+    rc::Rc,
+};
+
+use gl::types::{GLbitfield, GLenum, GLint, GLsizei, GLuint, GLDEBUGPROC};
+
+use crate::gl_utils::shaders::uniform::Uniform;
+use super::{Fence, GlBackend, GlCall};
+
+// Forwards every call to another backend and records it, with buffer contents and shader sources,
+// so the calls can be saved as a trace and replayed on another machine. See crate::trace
+pub struct TracingBackend {
+    inner: Rc<dyn GlBackend>,
+    calls: RefCell<Vec<GlCall>>,
+    recording: Cell<bool>,
+}
+
+impl TracingBackend {
+    pub fn new(inner: Rc<dyn GlBackend>) -> TracingBackend {
+        TracingBackend {
+            inner,
+            calls: RefCell::new(Vec::new()),
+            recording: Cell::new(true),
+        }
+    }
+
+    pub fn calls(&self) -> Vec<GlCall> {
+        self.calls.borrow().clone()
+    }
+
+    pub fn call_count(&self) -> usize {
+        self.calls.borrow().len()
+    }
+
+    // Keep forwarding calls, but stop recording them
+    pub fn stop(&self) {
+        self.recording.set(false);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.get()
+    }
+
+    fn record(&self, call: impl FnOnce() -> GlCall) {
+        if self.recording.get() {
+            self.calls.borrow_mut().push(call());
+        }
+    }
+}
+
+impl GlBackend for TracingBackend {
+    fn get_error(&self) -> GLenum {
+        self.inner.get_error()
+    }
+
+    fn get_integer(&self, parameter: GLenum) -> GLint {
+        self.inner.get_integer(parameter)
+    }
+
+    fn get_integer_i(&self, parameter: GLenum, index: GLuint) -> GLint {
+        self.inner.get_integer_i(parameter, index)
+    }
+
+    fn get_string(&self, name: GLenum) -> Option<String> {
+        self.inner.get_string(name)
+    }
+
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> Option<String> {
+        self.inner.get_string_i(name, index)
+    }
+
+    fn enable(&self, capability: GLenum) {
+        self.inner.enable(capability);
+        self.record(|| GlCall::Enable(capability));
+    }
+
+    fn disable(&self, capability: GLenum) {
+        self.inner.disable(capability);
+        self.record(|| GlCall::Disable(capability));
+    }
+
+    fn blend_func(&self, source: GLenum, destination: GLenum) {
+        self.inner.blend_func(source, destination);
+        self.record(|| GlCall::BlendFunc { source, destination });
+    }
+
+    fn polygon_mode(&self, face: GLenum, mode: GLenum) {
+        self.inner.polygon_mode(face, mode);
+        self.record(|| GlCall::PolygonMode { face, mode });
+    }
+
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        self.inner.viewport(x, y, width, height);
+        self.record(|| GlCall::Viewport { x, y, width, height });
+    }
+
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.inner.clear_color(r, g, b, a);
+        self.record(|| GlCall::ClearColor([r, g, b, a]));
+    }
+
+    fn clear(&self, mask: GLbitfield) {
+        self.inner.clear(mask);
+        self.record(|| GlCall::Clear(mask));
+    }
+
+    fn finish(&self) {
+        self.inner.finish();
+        self.record(|| GlCall::Finish);
+    }
+
+    fn gen_vertex_arrays(&self, ids: &mut [GLuint]) {
+        self.inner.gen_vertex_arrays(ids);
+        self.record(|| GlCall::GenVertexArrays(ids.to_vec()));
+    }
+
+    fn delete_vertex_arrays(&self, ids: &[GLuint]) {
+        self.inner.delete_vertex_arrays(ids);
+        self.record(|| GlCall::DeleteVertexArrays(ids.to_vec()));
+    }
+
+    fn bind_vertex_array(&self, id: GLuint) {
+        self.inner.bind_vertex_array(id);
+        self.record(|| GlCall::BindVertexArray(id));
+    }
+
+    fn enable_vertex_attrib_array(&self, index: GLuint) {
+        self.inner.enable_vertex_attrib_array(index);
+        self.record(|| GlCall::EnableVertexAttribArray(index));
+    }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, components: GLint, kind: GLenum, normalized: bool, stride: GLsizei, offset: usize) {
+        self.inner.vertex_attrib_pointer(index, components, kind, normalized, stride, offset);
+        self.record(|| GlCall::VertexAttribPointer { index, components, kind, normalized, stride, offset });
+    }
+
+    fn gen_buffers(&self, ids: &mut [GLuint]) {
+        self.inner.gen_buffers(ids);
+        self.record(|| GlCall::GenBuffers(ids.to_vec()));
+    }
+
+    fn delete_buffers(&self, ids: &[GLuint]) {
+        self.inner.delete_buffers(ids);
+        self.record(|| GlCall::DeleteBuffers(ids.to_vec()));
+    }
+
+    fn bind_buffer(&self, target: GLenum, id: GLuint) {
+        self.inner.bind_buffer(target, id);
+        self.record(|| GlCall::BindBuffer { target, id });
+    }
+
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum) {
+        self.inner.buffer_data(target, data, usage);
+        self.record(|| GlCall::BufferData { target, data: data.to_vec(), usage });
+    }
+
+    fn allocate_buffer(&self, target: GLenum, size: usize, usage: GLenum) {
+        self.inner.allocate_buffer(target, size, usage);
+        self.record(|| GlCall::AllocateBuffer { target, size, usage });
+    }
+
+    fn get_buffer_sub_data(&self, target: GLenum, offset: usize, data: &mut [u8]) {
+        self.inner.get_buffer_sub_data(target, offset, data);
+    }
+
+    fn gen_framebuffers(&self, ids: &mut [GLuint]) {
+        self.inner.gen_framebuffers(ids);
+        self.record(|| GlCall::GenFramebuffers(ids.to_vec()));
+    }
+
+    fn delete_framebuffers(&self, ids: &[GLuint]) {
+        self.inner.delete_framebuffers(ids);
+        self.record(|| GlCall::DeleteFramebuffers(ids.to_vec()));
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint) {
+        self.inner.bind_framebuffer(target, id);
+        self.record(|| GlCall::BindFramebuffer { target, id });
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        self.inner.check_framebuffer_status(target)
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint) {
+        self.inner.framebuffer_renderbuffer(target, attachment, renderbuffer);
+        self.record(|| GlCall::FramebufferRenderbuffer { target, attachment, renderbuffer });
+    }
+
+    fn blit_framebuffer(&self, source: [GLint; 4], destination: [GLint; 4], mask: GLbitfield, filter: GLenum) {
+        self.inner.blit_framebuffer(source, destination, mask, filter);
+        self.record(|| GlCall::BlitFramebuffer { source, destination, mask, filter });
+    }
+
+    fn gen_renderbuffers(&self, ids: &mut [GLuint]) {
+        self.inner.gen_renderbuffers(ids);
+        self.record(|| GlCall::GenRenderbuffers(ids.to_vec()));
+    }
+
+    fn delete_renderbuffers(&self, ids: &[GLuint]) {
+        self.inner.delete_renderbuffers(ids);
+        self.record(|| GlCall::DeleteRenderbuffers(ids.to_vec()));
+    }
+
+    fn bind_renderbuffer(&self, id: GLuint) {
+        self.inner.bind_renderbuffer(id);
+        self.record(|| GlCall::BindRenderbuffer(id));
+    }
+
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei) {
+        self.inner.renderbuffer_storage_multisample(samples, format, width, height);
+        self.record(|| GlCall::RenderbufferStorageMultisample { samples, format, width, height });
+    }
+
+    fn pixel_store(&self, parameter: GLenum, value: GLint) {
+        self.inner.pixel_store(parameter, value);
+        self.record(|| GlCall::PixelStore { parameter, value });
+    }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, pixels: &mut [u8]) {
+        self.inner.read_pixels(x, y, width, height, format, kind, pixels);
+        self.record(|| GlCall::ReadPixels { x, y, width, height, format, kind });
+    }
+
+    fn read_pixels_to_buffer(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, offset: usize) {
+        self.inner.read_pixels_to_buffer(x, y, width, height, format, kind, offset);
+        self.record(|| GlCall::ReadPixelsToBuffer { x, y, width, height, format, kind, offset });
+    }
+
+    fn fence_sync(&self) -> Fence {
+        let fence = self.inner.fence_sync();
+        self.record(|| GlCall::FenceSync(fence.0));
+        fence
+    }
+
+    fn client_wait_sync(&self, fence: Fence, flags: GLbitfield, timeout_ns: u64) -> GLenum {
+        self.inner.client_wait_sync(fence, flags, timeout_ns)
+    }
+
+    fn delete_sync(&self, fence: Fence) {
+        self.inner.delete_sync(fence);
+        self.record(|| GlCall::DeleteSync(fence.0));
+    }
+
+    fn create_shader(&self, kind: GLenum) -> GLuint {
+        let id = self.inner.create_shader(kind);
+        self.record(|| GlCall::CreateShader { kind, id });
+        id
+    }
+
+    fn shader_source(&self, shader: GLuint, source: &CStr) {
+        self.inner.shader_source(shader, source);
+        self.record(|| GlCall::ShaderSource { shader, source: source.to_string_lossy().into_owned() });
+    }
+
+    fn compile_shader(&self, shader: GLuint) {
+        self.inner.compile_shader(shader);
+        self.record(|| GlCall::CompileShader(shader));
+    }
+
+    fn get_shader_iv(&self, shader: GLuint, parameter: GLenum) -> GLint {
+        self.inner.get_shader_iv(shader, parameter)
+    }
+
+    fn shader_info_log(&self, shader: GLuint) -> String {
+        self.inner.shader_info_log(shader)
+    }
+
+    fn delete_shader(&self, shader: GLuint) {
+        self.inner.delete_shader(shader);
+        self.record(|| GlCall::DeleteShader(shader));
+    }
+
+    fn create_program(&self) -> GLuint {
+        let id = self.inner.create_program();
+        self.record(|| GlCall::CreateProgram(id));
+        id
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) {
+        self.inner.attach_shader(program, shader);
+        self.record(|| GlCall::AttachShader { program, shader });
+    }
+
+    fn link_program(&self, program: GLuint) {
+        self.inner.link_program(program);
+        self.record(|| GlCall::LinkProgram(program));
+    }
+
+    fn get_program_iv(&self, program: GLuint, parameter: GLenum) -> GLint {
+        self.inner.get_program_iv(program, parameter)
+    }
+
+    fn program_info_log(&self, program: GLuint) -> String {
+        self.inner.program_info_log(program)
+    }
+
+    fn use_program(&self, program: GLuint) {
+        self.inner.use_program(program);
+        self.record(|| GlCall::UseProgram(program));
+    }
+
+    fn delete_program(&self, program: GLuint) {
+        self.inner.delete_program(program);
+        self.record(|| GlCall::DeleteProgram(program));
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &CStr) -> GLint {
+        let location = self.inner.get_uniform_location(program, name);
+        self.record(|| GlCall::GetUniformLocation { program, name: name.to_string_lossy().into_owned(), location });
+        location
+    }
+
+    fn uniform(&self, location: GLint, value: Uniform) {
+        self.inner.uniform(location, value);
+        self.record(|| {
+            let program = self.inner.get_integer(gl::CURRENT_PROGRAM) as GLuint;
+            GlCall::Uniform { program, location, value }
+        });
+    }
+
+    fn gen_queries(&self, ids: &mut [GLuint]) {
+        self.inner.gen_queries(ids);
+        self.record(|| GlCall::GenQueries(ids.to_vec()));
+    }
+
+    fn delete_queries(&self, ids: &[GLuint]) {
+        self.inner.delete_queries(ids);
+        self.record(|| GlCall::DeleteQueries(ids.to_vec()));
+    }
+
+    fn query_counter(&self, id: GLuint, target: GLenum) {
+        self.inner.query_counter(id, target);
+        self.record(|| GlCall::QueryCounter { id, target });
+    }
+
+    fn get_query_object_iv(&self, id: GLuint, parameter: GLenum) -> GLint {
+        self.inner.get_query_object_iv(id, parameter)
+    }
+
+    fn get_query_object_u64(&self, id: GLuint, parameter: GLenum) -> u64 {
+        self.inner.get_query_object_u64(id, parameter)
+    }
+
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str) {
+        self.inner.object_label(identifier, id, label);
+        self.record(|| GlCall::ObjectLabel { identifier, id, label: label.to_string() });
+    }
+
+    fn push_debug_group(&self, message: &str) {
+        self.inner.push_debug_group(message);
+        self.record(|| GlCall::PushDebugGroup(message.to_string()));
+    }
+
+    fn pop_debug_group(&self) {
+        self.inner.pop_debug_group();
+        self.record(|| GlCall::PopDebugGroup);
+    }
+
+    unsafe fn debug_message_callback(&self, callback: GLDEBUGPROC, user_param: *const c_void) {
+        self.inner.debug_message_callback(callback, user_param);
+        self.record(|| GlCall::DebugMessageCallback(callback.is_some()));
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: GLsizei, kind: GLenum, offset: usize) {
+        self.inner.draw_elements(mode, count, kind, offset);
+        self.record(|| GlCall::DrawElements { mode, count, kind, offset });
+    }
+}
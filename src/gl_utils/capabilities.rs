@@ -0,0 +1,132 @@
+use std::{collections::BTreeSet, fmt};
+
+use gl::types::GLuint;
+
+use super::backend::{self, GlBackend};
+
+// What the current context supports. Drivers differ a lot, especially the software ones, so
+// check here before using anything newer than the context version we ask for
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    pub version: String,
+    pub major: u32,
+    pub minor: u32,
+    pub vendor: String,
+    pub renderer: String,
+    pub glsl_version: String,
+    pub extensions: BTreeSet<String>,
+    pub limits: Limits,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub max_texture_size: i32,
+    pub max_uniform_block_size: i32,
+    pub max_vertex_attribs: i32,
+    pub max_samples: i32,
+    // All zero without compute shader support
+    pub max_compute_work_group_count: [i32; 3],
+    pub max_compute_work_group_size: [i32; 3],
+    pub max_compute_work_group_invocations: i32,
+}
+
+impl Capabilities {
+    // Needs a current context with the function pointers loaded
+    pub fn query() -> Capabilities {
+        Capabilities::query_with(backend::current().as_ref())
+    }
+
+    pub fn query_with(gl: &dyn GlBackend) -> Capabilities {
+        let get_string = |name| gl.get_string(name).unwrap_or_else(|| "<unknown>".to_string());
+        let version = get_string(gl::VERSION);
+        let (major, minor) = (gl.get_integer(gl::MAJOR_VERSION) as u32, gl.get_integer(gl::MINOR_VERSION) as u32);
+
+        let extension_count = gl.get_integer(gl::NUM_EXTENSIONS).max(0) as GLuint;
+        let extensions = (0..extension_count)
+            .filter_map(|i| gl.get_string_i(gl::EXTENSIONS, i))
+            .collect();
+
+        let mut capabilities = Capabilities {
+            version,
+            major,
+            minor,
+            vendor: get_string(gl::VENDOR),
+            renderer: get_string(gl::RENDERER),
+            glsl_version: get_string(gl::SHADING_LANGUAGE_VERSION),
+            extensions,
+            limits: Limits {
+                max_texture_size: gl.get_integer(gl::MAX_TEXTURE_SIZE),
+                max_uniform_block_size: gl.get_integer(gl::MAX_UNIFORM_BLOCK_SIZE),
+                max_vertex_attribs: gl.get_integer(gl::MAX_VERTEX_ATTRIBS),
+                max_samples: gl.get_integer(gl::MAX_SAMPLES),
+                ..Limits::default()
+            },
+        };
+
+        // Querying these without compute support is an error
+        if capabilities.supports_compute() {
+            let limits = &mut capabilities.limits;
+            for i in 0..3 {
+                limits.max_compute_work_group_count[i] = gl.get_integer_i(gl::MAX_COMPUTE_WORK_GROUP_COUNT, i as GLuint);
+                limits.max_compute_work_group_size[i] = gl.get_integer_i(gl::MAX_COMPUTE_WORK_GROUP_SIZE, i as GLuint);
+            }
+            limits.max_compute_work_group_invocations = gl.get_integer(gl::MAX_COMPUTE_WORK_GROUP_INVOCATIONS);
+        }
+
+        capabilities
+    }
+
+    pub fn supports_version(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    pub fn supports_compute(&self) -> bool {
+        self.supports_version(4, 3) || self.has_extension("GL_ARB_compute_shader")
+    }
+
+    // Debug callbacks, object labels and debug groups
+    pub fn supports_debug_output(&self) -> bool {
+        self.supports_version(4, 3) || self.has_extension("GL_KHR_debug")
+    }
+
+    pub fn supports_timer_queries(&self) -> bool {
+        self.supports_version(3, 3) || self.has_extension("GL_ARB_timer_query")
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limits = &self.limits;
+        writeln!(f, "OpenGL version:   {}", self.version)?;
+        writeln!(f, "Vendor:           {}", self.vendor)?;
+        writeln!(f, "Renderer:         {}", self.renderer)?;
+        writeln!(f, "GLSL version:     {}", self.glsl_version)?;
+        writeln!(f, "Limits:")?;
+        writeln!(f, "  Max texture size:       {}", limits.max_texture_size)?;
+        writeln!(f, "  Max uniform block size: {}", limits.max_uniform_block_size)?;
+        writeln!(f, "  Max vertex attributes:  {}", limits.max_vertex_attribs)?;
+        writeln!(f, "  Max MSAA samples:       {}", limits.max_samples)?;
+        if self.supports_compute() {
+            writeln!(f, "  Max compute work groups:           {:?}", limits.max_compute_work_group_count)?;
+            writeln!(f, "  Max compute work group size:       {:?}", limits.max_compute_work_group_size)?;
+            writeln!(f, "  Max compute work group invocations: {}", limits.max_compute_work_group_invocations)?;
+        }
+        writeln!(f, "Features:")?;
+        writeln!(f, "  Compute shaders: {}", yes_no(self.supports_compute()))?;
+        writeln!(f, "  Debug output:    {}", yes_no(self.supports_debug_output()))?;
+        writeln!(f, "  Timer queries:   {}", yes_no(self.supports_timer_queries()))?;
+        writeln!(f, "Extensions ({}):", self.extensions.len())?;
+        for extension in self.extensions.iter() {
+            writeln!(f, "  {}", extension)?;
+        }
+        Ok(())
+    }
+}
+
+fn yes_no(supported: bool) -> &'static str {
+    if supported { "yes" } else { "no" }
+}
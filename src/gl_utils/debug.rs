@@ -0,0 +1,418 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    ffi::{c_void, CStr},
+    fmt, ptr,
+    rc::Rc,
+    sync::Mutex,
+};
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+use serde::Deserialize;
+
+use super::backend::{self, GlBackend};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugSource {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+impl DebugSource {
+    pub fn from_gl(source: GLenum) -> DebugSource {
+        match source {
+            gl::DEBUG_SOURCE_API => DebugSource::Api,
+            gl::DEBUG_SOURCE_WINDOW_SYSTEM => DebugSource::WindowSystem,
+            gl::DEBUG_SOURCE_SHADER_COMPILER => DebugSource::ShaderCompiler,
+            gl::DEBUG_SOURCE_THIRD_PARTY => DebugSource::ThirdParty,
+            gl::DEBUG_SOURCE_APPLICATION => DebugSource::Application,
+            _ => DebugSource::Other,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugType {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+impl DebugType {
+    pub fn from_gl(e_type: GLenum) -> DebugType {
+        match e_type {
+            gl::DEBUG_TYPE_ERROR => DebugType::Error,
+            gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => DebugType::DeprecatedBehavior,
+            gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => DebugType::UndefinedBehavior,
+            gl::DEBUG_TYPE_PORTABILITY => DebugType::Portability,
+            gl::DEBUG_TYPE_PERFORMANCE => DebugType::Performance,
+            gl::DEBUG_TYPE_MARKER => DebugType::Marker,
+            gl::DEBUG_TYPE_PUSH_GROUP => DebugType::PushGroup,
+            gl::DEBUG_TYPE_POP_GROUP => DebugType::PopGroup,
+            _ => DebugType::Other,
+        }
+    }
+}
+
+// Declared from least to most severe, so severities can be compared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    pub fn from_gl(severity: GLenum) -> DebugSeverity {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugMessage {
+    pub id: u32,
+    pub source: DebugSource,
+    pub kind: DebugType,
+    pub severity: DebugSeverity,
+    // With the labels of the objects it mentions added, see annotate
+    pub message: String,
+    // The debug groups the message was raised in, outermost first
+    pub groups: Vec<String>,
+}
+
+impl fmt::Display for DebugMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:?} {:?} {:?} #{}] {}", self.severity, self.source, self.kind, self.id, self.message)?;
+        if !self.groups.is_empty() {
+            write!(f, " (in {})", self.groups.join(" > "))?;
+        }
+        Ok(())
+    }
+}
+
+// What to do with messages that make it through the filters
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugMode {
+    // Print them, errors and high severity messages to stderr and the rest to stdout
+    Log,
+    // Log them and panic on the render thread at the next check
+    Break,
+    // Keep them in memory until taken, for tests
+    Collect,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DebugOutputConfig {
+    pub mode: DebugMode,
+    // Messages less severe than this are dropped
+    pub min_severity: DebugSeverity,
+    // Only let these through, empty allows everything
+    pub sources: Vec<DebugSource>,
+    pub types: Vec<DebugType>,
+    // Message IDs to drop, for known driver noise
+    pub ignore_ids: Vec<u32>,
+    // Only report the first message with a given source, type and ID
+    pub deduplicate: bool,
+}
+
+impl Default for DebugOutputConfig {
+    fn default() -> DebugOutputConfig {
+        DebugOutputConfig {
+            mode: DebugMode::Log,
+            min_severity: DebugSeverity::Low,
+            sources: Vec::new(),
+            types: Vec::new(),
+            ignore_ids: Vec::new(),
+            deduplicate: true,
+        }
+    }
+}
+
+impl DebugOutputConfig {
+    pub fn allows(&self, message: &DebugMessage) -> bool {
+        message.severity >= self.min_severity
+            && (self.sources.is_empty() || self.sources.contains(&message.source))
+            && (self.types.is_empty() || self.types.contains(&message.kind))
+            && !self.ignore_ids.contains(&message.id)
+    }
+}
+
+#[derive(Default)]
+struct DebugState {
+    seen: HashSet<(DebugSource, DebugType, u32)>,
+    duplicates: usize,
+    collected: Vec<DebugMessage>,
+    // The first message that should break, raised by check
+    pending_break: Option<DebugMessage>,
+    // Followed through the push and pop messages the driver sends for every group
+    groups: Vec<String>,
+}
+
+struct Shared {
+    config: DebugOutputConfig,
+    state: Mutex<DebugState>,
+}
+
+// Owns the debug message callback of the current context. Has to stay alive, and on the same
+// thread as the context, for as long as the callback is installed. Dropping it removes the
+// callback, so drop it before the context
+pub struct DebugOutput {
+    shared: Box<Shared>,
+    backend: Rc<dyn GlBackend>,
+}
+
+impl DebugOutput {
+    // Install the callback on the current context. Output is made synchronous, so messages
+    // arrive on this thread in the middle of the call that caused them
+    pub fn install(config: DebugOutputConfig) -> DebugOutput {
+        let shared = Box::new(Shared { config, state: Mutex::new(DebugState::default()) });
+        let backend = backend::current();
+        backend.enable(gl::DEBUG_OUTPUT);
+        backend.enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        // The box keeps Shared in place until drop removes the callback
+        unsafe { backend.debug_message_callback(Some(debug_callback), &*shared as *const Shared as *const c_void) };
+        DebugOutput { shared, backend }
+    }
+
+    pub fn config(&self) -> &DebugOutputConfig {
+        &self.shared.config
+    }
+
+    // Panicking inside the callback would abort, since it can't unwind through the driver.
+    // In break mode the runner calls this after every frame to panic on the render thread instead
+    pub fn check(&self) {
+        let pending_break = self.state().pending_break.take();
+        if let Some(message) = pending_break {
+            panic!("OpenGL debug message: {}", message);
+        }
+    }
+
+    // Messages gathered in collect mode since the last call
+    pub fn take_collected(&self) -> Vec<DebugMessage> {
+        std::mem::take(&mut self.state().collected)
+    }
+
+    // How many messages were dropped for repeating one that was already reported
+    pub fn duplicates(&self) -> usize {
+        self.state().duplicates
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, DebugState> {
+        // A panic while holding the lock can't leave the state half updated
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for DebugOutput {
+    fn drop(&mut self) {
+        unsafe { self.backend.debug_message_callback(None, ptr::null()) };
+    }
+}
+
+impl Shared {
+    fn handle(&self, mut message: DebugMessage) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        message.groups = state.groups.clone();
+        match message.kind {
+            DebugType::PushGroup => state.groups.push(message.message.clone()),
+            DebugType::PopGroup => { state.groups.pop(); },
+            _ => (),
+        }
+
+        if !self.config.allows(&message) {
+            return;
+        }
+        if self.config.deduplicate && !state.seen.insert((message.source, message.kind, message.id)) {
+            state.duplicates += 1;
+            return;
+        }
+
+        match self.config.mode {
+            DebugMode::Log => log(&message),
+            DebugMode::Break => {
+                log(&message);
+                if state.pending_break.is_none() {
+                    state.pending_break = Some(message);
+                }
+            },
+            DebugMode::Collect => state.collected.push(message),
+        }
+    }
+}
+
+fn log(message: &DebugMessage) {
+    if message.kind == DebugType::Error || message.severity == DebugSeverity::High {
+        eprintln!("GL: {}", message);
+    } else {
+        println!("GL: {}", message);
+    }
+}
+
+extern "system" fn debug_callback(
+    source: GLenum, e_type: GLenum, id: GLuint,
+    severity: GLenum, length: GLsizei,
+    msg: *const GLchar, user_param: *mut c_void
+) {
+    if msg.is_null() || user_param.is_null() {
+        return;
+    }
+
+    // The message is owned by the driver and only valid during the call, so copy it out
+    let message = unsafe {
+        if length >= 0 {
+            let bytes = std::slice::from_raw_parts(msg as *const u8, length as usize);
+            String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string()
+        } else {
+            CStr::from_ptr(msg).to_string_lossy().into_owned()
+        }
+    };
+
+    let shared = unsafe { &*(user_param as *const Shared) };
+    let kind = DebugType::from_gl(e_type);
+    shared.handle(DebugMessage {
+        id,
+        source: DebugSource::from_gl(source),
+        kind,
+        severity: DebugSeverity::from_gl(severity),
+        // Group messages are the group names, which shouldn't change
+        message: match kind {
+            DebugType::PushGroup | DebugType::PopGroup => message,
+            _ => annotate(&message),
+        },
+        groups: Vec::new(),
+    });
+}
+
+thread_local! {
+    // Labels and groups need KHR_debug, so they're off until the runner has checked for it.
+    // Like backends, they're per thread since contexts are
+    static LABELS_ENABLED: Cell<bool> = const { Cell::new(false) };
+    // Every label handed out on this thread, for annotating debug messages. Synchronous debug
+    // output calls the callback on the thread that made the call
+    static LABELS: RefCell<HashMap<(GLenum, GLuint), String>> = RefCell::new(HashMap::new());
+}
+
+// Turn object labels and debug groups on for this thread, when the context supports them
+pub fn enable_labels(enabled: bool) {
+    LABELS_ENABLED.with(|labels_enabled| labels_enabled.set(enabled));
+}
+
+pub fn labels_enabled() -> bool {
+    LABELS_ENABLED.with(Cell::get)
+}
+
+// Name an object with glObjectLabel. The identifier is the kind of object, like gl::BUFFER
+pub fn label(backend: &dyn GlBackend, identifier: GLenum, id: GLuint, name: &str) {
+    if !labels_enabled() || id == 0 {
+        return;
+    }
+    backend.object_label(identifier, id, name);
+    LABELS.with(|labels| labels.borrow_mut().insert((identifier, id), name.to_string()));
+}
+
+// Call when deleting a labeled object, so a new object with the same id isn't mistaken for it
+pub fn forget_label(identifier: GLenum, id: GLuint) {
+    LABELS.with(|labels| labels.borrow_mut().remove(&(identifier, id)));
+}
+
+pub fn object_label(identifier: GLenum, id: GLuint) -> Option<String> {
+    LABELS.with(|labels| labels.borrow().get(&(identifier, id)).cloned())
+}
+
+// How drivers refer to objects in their messages, like "buffer object 3" or "program 5"
+const OBJECT_NAMES: [(&str, GLenum); 9] = [
+    ("renderbuffer", gl::RENDERBUFFER),
+    ("framebuffer", gl::FRAMEBUFFER),
+    ("buffer", gl::BUFFER),
+    ("program", gl::PROGRAM),
+    ("shader", gl::SHADER),
+    ("texture", gl::TEXTURE),
+    ("array", gl::VERTEX_ARRAY),
+    ("vao", gl::VERTEX_ARRAY),
+    ("query", gl::QUERY),
+];
+
+// Add the labels of the objects a driver message mentions after their ids, so "buffer object 3
+// will use VIDEO memory" becomes "buffer object 3 "mesh vertices" will use VIDEO memory"
+pub fn annotate(message: &str) -> String {
+    annotate_with(message, object_label)
+}
+
+pub fn annotate_with(message: &str, lookup: impl Fn(GLenum, GLuint) -> Option<String>) -> String {
+    let words: Vec<&str> = message.split(' ').collect();
+    let kind_of = |word: &str| {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+        OBJECT_NAMES.iter().find(|(name, _)| word.ends_with(name)).map(|&(_, identifier)| identifier)
+    };
+
+    let mut annotated = Vec::with_capacity(words.len());
+    for (i, word) in words.iter().enumerate() {
+        let digits = word.trim_start_matches(|c: char| !c.is_ascii_digit());
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        let id = digits[..end].parse::<GLuint>().ok().filter(|_| digits.len() + 1 >= word.len());
+
+        // The kind is the word before the id, or the one before "object"
+        let kind = match (i.checked_sub(1).map(|j| words[j]), i.checked_sub(2).map(|j| words[j])) {
+            (Some(object), Some(kind)) if object.eq_ignore_ascii_case("object") => kind_of(kind),
+            (Some(kind), _) => kind_of(kind),
+            _ => None,
+        };
+
+        match (id, kind) {
+            (Some(id), Some(kind)) => match lookup(kind, id) {
+                Some(label) => {
+                    let (number, rest) = word.split_at(word.len() - digits.len() + end);
+                    annotated.push(format!("{} \"{}\"{}", number, label, rest));
+                },
+                None => annotated.push(word.to_string()),
+            },
+            _ => annotated.push(word.to_string()),
+        }
+    }
+    annotated.join(" ")
+}
+
+// Wraps the calls until it is dropped in a debug group, which debuggers show as a folder and
+// debug messages mention. Does nothing unless labels are enabled
+#[must_use = "the group ends when it is dropped"]
+pub struct DebugGroup {
+    backend: Option<Rc<dyn GlBackend>>,
+}
+
+pub fn group(name: &str) -> DebugGroup {
+    if !labels_enabled() {
+        return DebugGroup { backend: None };
+    }
+    let backend = backend::current();
+    backend.push_debug_group(name);
+    DebugGroup { backend: Some(backend) }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if let Some(backend) = &self.backend {
+            backend.pop_debug_group();
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::rc::Rc;
+
+use gl::types::GLuint;
+use image::RgbaImage;
+
+use crate::error::{Error, Result};
+use crate::screenshot;
+use super::{backend::{self, GlBackend}, bindable::Bindable, debug};
+
+// An offscreen render target with a color and depth buffer. With multisampling, drawing goes to
+// multisampled renderbuffers that are resolved into a plain framebuffer before reading back
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    // The framebuffer that gets drawn to
+    draw: Target,
+    // Only with multisampling, what the draw target is resolved into
+    resolve: Option<Target>,
+}
+
+struct Target {
+    id: GLuint,
+    // Color and depth
+    renderbuffers: [GLuint; 2],
+    backend: Rc<dyn GlBackend>,
+}
+
+impl Target {
+    // The name labels the framebuffer and its renderbuffers
+    fn new(width: u32, height: u32, samples: u32, name: &str) -> Result<Target> {
+        let backend = backend::current();
+        let mut target = Target { id: 0, renderbuffers: [0; 2], backend: Rc::clone(&backend) };
+        backend.gen_framebuffers(std::slice::from_mut(&mut target.id));
+        backend.bind_framebuffer(gl::FRAMEBUFFER, target.id);
+        backend.gen_renderbuffers(&mut target.renderbuffers);
+
+        let attachments = [(gl::RGBA8, gl::COLOR_ATTACHMENT0), (gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL_ATTACHMENT)];
+        for (&renderbuffer, &(format, attachment)) in target.renderbuffers.iter().zip(attachments.iter()) {
+            backend.bind_renderbuffer(renderbuffer);
+            backend.renderbuffer_storage_multisample(samples as i32, format, width as i32, height as i32);
+            backend.framebuffer_renderbuffer(gl::FRAMEBUFFER, attachment, renderbuffer);
+        }
+        backend.bind_renderbuffer(0);
+
+        debug::label(backend.as_ref(), gl::FRAMEBUFFER, target.id, name);
+        debug::label(backend.as_ref(), gl::RENDERBUFFER, target.renderbuffers[0], &format!("{} color", name));
+        debug::label(backend.as_ref(), gl::RENDERBUFFER, target.renderbuffers[1], &format!("{} depth", name));
+
+        let status = backend.check_framebuffer_status(gl::FRAMEBUFFER);
+        backend.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        backend.check_error("Framebuffer::new")?;
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(Error::Texture(format!("framebuffer is incomplete, status 0x{:04X}", status)));
+        }
+
+        Ok(target)
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        debug::forget_label(gl::FRAMEBUFFER, self.id);
+        for &renderbuffer in &self.renderbuffers {
+            debug::forget_label(gl::RENDERBUFFER, renderbuffer);
+        }
+        self.backend.delete_framebuffers(&[self.id]);
+        self.backend.delete_renderbuffers(&self.renderbuffers);
+    }
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32, samples: u32) -> Result<Framebuffer> {
+        if width == 0 || height == 0 {
+            return Err(Error::Texture(format!("framebuffer size must be positive, got {}x{}", width, height)));
+        }
+
+        let draw = Target::new(width, height, samples, "offscreen framebuffer")?;
+        let resolve = if samples > 0 { Some(Target::new(width, height, 0, "offscreen resolve framebuffer")?) } else { None };
+        Ok(Framebuffer { width, height, samples, draw, resolve })
+    }
+
+    // Read the color buffer back into an image, top row first
+    pub fn read_pixels(&self) -> Result<RgbaImage> {
+        let backend = &self.draw.backend;
+        let previous = backend.get_integer(gl::FRAMEBUFFER_BINDING) as GLuint;
+
+        let source = match &self.resolve {
+            Some(resolve) => {
+                let (width, height) = (self.width as i32, self.height as i32);
+                backend.bind_framebuffer(gl::READ_FRAMEBUFFER, self.draw.id);
+                backend.bind_framebuffer(gl::DRAW_FRAMEBUFFER, resolve.id);
+                backend.blit_framebuffer([0, 0, width, height], [0, 0, width, height], gl::COLOR_BUFFER_BIT, gl::NEAREST);
+                resolve.id
+            },
+            None => self.draw.id,
+        };
+
+        backend.bind_framebuffer(gl::READ_FRAMEBUFFER, source);
+        let image = screenshot::read_pixels_with(backend.as_ref(), self.width, self.height, true);
+        backend.bind_framebuffer(gl::FRAMEBUFFER, previous);
+        image
+    }
+}
+
+impl Bindable for Framebuffer {
+    fn bind(&self) {
+        self.draw.backend.bind_framebuffer(gl::FRAMEBUFFER, self.draw.id);
+    }
+
+    fn unbind(&self) {
+        self.draw.backend.bind_framebuffer(gl::FRAMEBUFFER, 0);
+    }
+}
@@ -4,7 +4,7 @@ use core::ffi::c_void;
 use std::mem;
 
 pub fn byte_size_of_array<T>(val: &[T]) -> isize {
-    std::mem::size_of_val(&val[..]) as isize
+    std::mem::size_of_val(val) as isize
 }
 
 // Get the OpenGL-compatible pointer to an arbitrary array of numbers
@@ -21,3 +21,24 @@ pub fn size_of<T>() -> i32 {
 pub fn offset<T>(n: u32) -> *const c_void {
     (n * mem::size_of::<T>() as u32) as *const T as *const c_void
 }
+
+// View an array of plain numbers as its raw bytes, for uploading to a buffer
+pub fn as_bytes<T: Pod>(val: &[T]) -> &[u8] {
+    // Pod types have no padding, so every byte is initialized
+    unsafe { std::slice::from_raw_parts(val.as_ptr() as *const u8, mem::size_of_val(val)) }
+}
+
+// Types without padding or invalid bit patterns. Sealed, since implementing it for a struct
+// with padding would make as_bytes read uninitialized memory
+pub trait Pod: Copy + sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+impl sealed::Sealed for u8 {}
+impl sealed::Sealed for u32 {}
+impl sealed::Sealed for f32 {}
+impl Pod for u8 {}
+impl Pod for u32 {}
+impl Pod for f32 {}
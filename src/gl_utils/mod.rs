@@ -1,4 +1,9 @@
 pub mod bindable;
 pub mod triangle;
 pub mod helpers;
-pub mod shaders;
\ No newline at end of file
+pub mod shaders;
+pub mod debug;
+pub mod capabilities;
+pub mod framebuffer;
+pub mod backend;
+pub mod profiler;
@@ -0,0 +1,321 @@
+use std::{cell::RefCell, fmt, rc::Rc, time::Duration};
+
+use gl::types::GLuint;
+
+use super::{
+    backend::{self, GlBackend},
+    capabilities::Capabilities
+};
+
+// Frames of queries kept in flight. A frame's results are read when its slot comes around again,
+// by which time the GPU has almost always finished it, so reading them never stalls
+pub const FRAMES_IN_FLIGHT: usize = 3;
+
+// GPU time spent on a pass, with the start relative to the start of the frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    pub name: String,
+    // How many passes this one is nested in
+    pub depth: usize,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+// Where the GPU spent its time in one frame. Passes are in the order they started in
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuFrame {
+    pub frame: u64,
+    pub total: Duration,
+    pub passes: Vec<PassTiming>,
+}
+
+impl GpuFrame {
+    pub fn pass(&self, name: &str) -> Option<&PassTiming> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+}
+
+impl fmt::Display for GpuFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GPU frame {}: {:.3} ms", self.frame, self.total.as_secs_f64() * 1000.0)?;
+        for pass in &self.passes {
+            let indent = 2 * (pass.depth + 1);
+            write!(f, "\n{:indent$}{:<width$} {:>8.3} ms", "", pass.name, pass.duration.as_secs_f64() * 1000.0,
+                indent = indent, width = 28usize.saturating_sub(indent))?;
+        }
+        Ok(())
+    }
+}
+
+struct PendingPass {
+    name: String,
+    depth: usize,
+    begin: GLuint,
+    // Still 0 while the pass is open
+    end: GLuint,
+}
+
+// The queries of one frame, waiting for their results
+#[derive(Default)]
+struct PendingFrame {
+    frame: u64,
+    // 0 when the slot holds no frame
+    begin: GLuint,
+    end: GLuint,
+    passes: Vec<PendingPass>,
+}
+
+impl PendingFrame {
+    fn queries(&self) -> impl Iterator<Item = GLuint> + '_ {
+        std::iter::once(self.begin)
+            .chain(std::iter::once(self.end))
+            .chain(self.passes.iter().flat_map(|pass| [pass.begin, pass.end]))
+            .filter(|&id| id != 0)
+    }
+}
+
+struct State {
+    slots: Vec<PendingFrame>,
+    current: usize,
+    next_frame: u64,
+    in_frame: bool,
+    // Indices of the passes of the current frame that haven't ended yet
+    open: Vec<usize>,
+    // Queries whose results have been read, ready to be written again
+    free: Vec<GLuint>,
+    latest: Option<GpuFrame>,
+    dropped: u64,
+}
+
+// Measures how long the GPU spends on named passes with GL_TIMESTAMP queries. Timestamps are used
+// instead of GL_TIME_ELAPSED because elapsed queries can't be nested.
+//
+// Frames are bracketed by begin_frame and end_frame, and passes by scope, which can be nested.
+// Results show up in latest a few frames later. Without timer query support, or when created
+// with disabled, every call does nothing
+pub struct GpuProfiler {
+    backend: Option<Rc<dyn GlBackend>>,
+    state: RefCell<State>,
+}
+
+// Ends its pass when dropped
+#[must_use = "the pass ends when the scope is dropped"]
+pub struct GpuScope<'a> {
+    profiler: &'a GpuProfiler,
+}
+
+impl Drop for GpuScope<'_> {
+    fn drop(&mut self) {
+        self.profiler.end_pass();
+    }
+}
+
+impl GpuProfiler {
+    pub fn new(capabilities: &Capabilities) -> GpuProfiler {
+        if capabilities.supports_timer_queries() {
+            GpuProfiler::with_backend(Some(backend::current()))
+        } else {
+            GpuProfiler::disabled()
+        }
+    }
+
+    pub fn disabled() -> GpuProfiler {
+        GpuProfiler::with_backend(None)
+    }
+
+    fn with_backend(backend: Option<Rc<dyn GlBackend>>) -> GpuProfiler {
+        GpuProfiler {
+            backend,
+            state: RefCell::new(State {
+                slots: (0..FRAMES_IN_FLIGHT).map(|_| PendingFrame::default()).collect(),
+                current: 0,
+                next_frame: 0,
+                in_frame: false,
+                open: Vec::new(),
+                free: Vec::new(),
+                latest: None,
+                dropped: 0,
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    // Read the results of earlier frames that are ready, then start timing a new one
+    pub fn begin_frame(&self) {
+        let backend = match &self.backend {
+            Some(backend) => backend,
+            None => return,
+        };
+        let mut state = self.state.borrow_mut();
+        if state.in_frame {
+            return;
+        }
+
+        self.collect(&mut state, false);
+
+        // The GPU is more than FRAMES_IN_FLIGHT frames behind, give up on the oldest frame
+        // rather than waiting for it
+        let current = state.current;
+        if state.slots[current].begin != 0 {
+            let stale = std::mem::take(&mut state.slots[current]);
+            state.free.extend(stale.queries());
+            state.dropped += 1;
+        }
+
+        let begin = self.timestamp(backend.as_ref(), &mut state);
+        let frame = state.next_frame;
+        state.slots[current] = PendingFrame { frame, begin, end: 0, passes: Vec::new() };
+        state.next_frame += 1;
+        state.in_frame = true;
+    }
+
+    // Passes that are still open end here
+    pub fn end_frame(&self) {
+        let backend = match &self.backend {
+            Some(backend) => backend,
+            None => return,
+        };
+        let mut state = self.state.borrow_mut();
+        if !state.in_frame {
+            return;
+        }
+
+        while !state.open.is_empty() {
+            self.end_open_pass(backend.as_ref(), &mut state);
+        }
+        let end = self.timestamp(backend.as_ref(), &mut state);
+        let current = state.current;
+        state.slots[current].end = end;
+        state.current = (current + 1) % FRAMES_IN_FLIGHT;
+        state.in_frame = false;
+    }
+
+    // Time everything until the returned scope is dropped as a pass of the current frame.
+    // Passes outside of a frame aren't timed
+    pub fn scope(&self, name: &str) -> GpuScope<'_> {
+        self.begin_pass(name);
+        GpuScope { profiler: self }
+    }
+
+    // Like scope, for passes that can't be wrapped in one. Every begin_pass needs an end_pass
+    pub fn begin_pass(&self, name: &str) {
+        let backend = match &self.backend {
+            Some(backend) => backend,
+            None => return,
+        };
+        let mut state = self.state.borrow_mut();
+        if !state.in_frame {
+            return;
+        }
+
+        let begin = self.timestamp(backend.as_ref(), &mut state);
+        let depth = state.open.len();
+        let current = state.current;
+        let passes = &mut state.slots[current].passes;
+        passes.push(PendingPass { name: name.to_string(), depth, begin, end: 0 });
+        let index = passes.len() - 1;
+        state.open.push(index);
+    }
+
+    pub fn end_pass(&self) {
+        if let Some(backend) = &self.backend {
+            let mut state = self.state.borrow_mut();
+            if state.in_frame && !state.open.is_empty() {
+                self.end_open_pass(backend.as_ref(), &mut state);
+            }
+        }
+    }
+
+    // The most recent frame with results
+    pub fn latest(&self) -> Option<GpuFrame> {
+        self.state.borrow().latest.clone()
+    }
+
+    // Frames whose results weren't ready in time and were thrown away
+    pub fn dropped_frames(&self) -> u64 {
+        self.state.borrow().dropped
+    }
+
+    // Wait for the results of every frame still in flight, for when no more frames are coming
+    pub fn finish(&self) {
+        if self.backend.is_some() {
+            let mut state = self.state.borrow_mut();
+            self.collect(&mut state, true);
+        }
+    }
+
+    fn end_open_pass(&self, backend: &dyn GlBackend, state: &mut State) {
+        let end = self.timestamp(backend, state);
+        if let Some(index) = state.open.pop() {
+            let current = state.current;
+            state.slots[current].passes[index].end = end;
+        }
+    }
+
+    fn timestamp(&self, backend: &dyn GlBackend, state: &mut State) -> GLuint {
+        let id = state.free.pop().unwrap_or_else(|| {
+            let mut id = 0;
+            backend.gen_queries(std::slice::from_mut(&mut id));
+            id
+        });
+        backend.query_counter(id, gl::TIMESTAMP);
+        id
+    }
+
+    // Read every finished frame, oldest first. Timestamps are written in order, so a frame is
+    // done once its end query is
+    fn collect(&self, state: &mut State, wait: bool) {
+        let backend = match &self.backend {
+            Some(backend) => backend.as_ref(),
+            None => return,
+        };
+
+        let mut finished: Vec<usize> = (0..FRAMES_IN_FLIGHT)
+            .filter(|&slot| state.slots[slot].end != 0)
+            .filter(|&slot| wait || backend.get_query_object_iv(state.slots[slot].end, gl::QUERY_RESULT_AVAILABLE) != 0)
+            .collect();
+        finished.sort_by_key(|&slot| state.slots[slot].frame);
+
+        for slot in finished {
+            let pending = std::mem::take(&mut state.slots[slot]);
+            let time = |id: GLuint| backend.get_query_object_u64(id, gl::QUERY_RESULT);
+            let frame_start = time(pending.begin);
+            let passes = pending.passes.iter()
+                .filter(|pass| pass.end != 0)
+                .map(|pass| {
+                    let begin = time(pass.begin);
+                    PassTiming {
+                        name: pass.name.clone(),
+                        depth: pass.depth,
+                        start: Duration::from_nanos(begin.saturating_sub(frame_start)),
+                        duration: Duration::from_nanos(time(pass.end).saturating_sub(begin)),
+                    }
+                })
+                .collect();
+            state.latest = Some(GpuFrame {
+                frame: pending.frame,
+                total: Duration::from_nanos(time(pending.end).saturating_sub(frame_start)),
+                passes,
+            });
+            state.free.extend(pending.queries());
+        }
+    }
+}
+
+impl Drop for GpuProfiler {
+    fn drop(&mut self) {
+        if let Some(backend) = &self.backend {
+            let state = self.state.borrow();
+            let queries: Vec<GLuint> = state.slots.iter()
+                .flat_map(PendingFrame::queries)
+                .chain(state.free.iter().copied())
+                .collect();
+            if !queries.is_empty() {
+                backend.delete_queries(&queries);
+            }
+        }
+    }
+}
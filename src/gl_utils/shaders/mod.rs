@@ -1,3 +1,3 @@
 pub mod program;
-pub mod errors;
-pub mod shader_type;
\ No newline at end of file
+pub mod shader_type;
+pub mod uniform;
@@ -1,228 +1,205 @@
-use crate::gl_utils::shaders::errors::ShaderProgramError;
-use crate::gl_utils::bindable::Bindable;
-use super::{shader_type::ShaderType, errors::GlUniformError};
-
-use gl::types::{
-    GLboolean, 
-    GLsizei, 
-    GLuint, 
-    GLint
+use crate::error::{Context, Error, GlError, ProgramError, Result, ShaderError};
+use crate::profiling;
+use crate::gl_utils::{
+    bindable::Bindable,
+    debug,
+    backend::{self, GlBackend}
 };
+use super::{shader_type::ShaderType, uniform::Uniform};
+
+use gl::types::{GLint, GLuint};
 
 use std::{
     collections::HashMap, 
     ffi::CString, 
-    ptr, 
-    path::Path
+    path::Path,
+    rc::Rc
 };
 
 pub struct Program {
     pub program_id: u32,
-    uniforms: HashMap<String, GLint> 
+    uniforms: HashMap<String, GLint>,
+    backend: Rc<dyn GlBackend>,
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        debug::forget_label(gl::PROGRAM, self.program_id);
+        self.backend.delete_program(self.program_id);
+    }
 }
 
 impl Bindable for Program {
     fn bind(&self) {
-        unsafe {
-            gl::UseProgram(self.program_id);
-        }
+        self.backend.use_program(self.program_id);
     }
 
     fn unbind(&self) {
-        unsafe {
-            gl::UseProgram(0);
-        }
+        self.backend.use_program(0);
     }
 }
 
 impl Program {
-    pub fn locate_uniform(&mut self, name: &str) -> Result<(), ShaderProgramError> {
-        if let Some(_) = self.uniforms.get(name) {
+    pub fn locate_uniform(&mut self, name: &str) -> Result<()> {
+        if self.uniforms.contains_key(name) {
             return Ok(());
         }
 
-        let target_location = unsafe { 
-            let c_name = match CString::new(name) {
-                Ok(c_name) => c_name,
-                Err(e) => return Err(ShaderProgramError::CStr(e))
-            };
-
-            let location = gl::GetUniformLocation(self.program_id, c_name.as_ptr());
-            let error = gl::GetError();
-            if error != gl::NO_ERROR {
-                return Err(ShaderProgramError::GlUniform(GlUniformError::new(error)));
-            }
-
-            location
-        };
+        let c_name = CString::new(name).map_err(ProgramError::Nul)?;
+        let target_location = self.backend.get_uniform_location(self.program_id, &c_name);
+        self.backend.check_error("glGetUniformLocation").map_err(|source| uniform_error(name, source))?;
 
         if target_location < 0 {
-            return Err(ShaderProgramError::UniformNotFound);
+            return Err(ProgramError::UniformNotFound(name.to_string()).into());
         }
 
         self.uniforms.insert(name.to_string(), target_location);
 
-        return Ok(());
-    }
-
-    // TODO: solve duplicate code in set_uniform...
-
-    pub fn set_uniform1<T>(&self, name: &str, value: T, assign_fn: unsafe fn(GLint, T) -> ()) -> Result<(), ShaderProgramError> {
-        let uniform_location: i32 = match self.uniforms.get(name) {
-            Some(u) => *u,
-            None => return Err(ShaderProgramError::UniformNotFound)
-        };
-
-        unsafe {
-            let mut active_program: GLint = 0;
-            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut active_program);
-
-            gl::UseProgram(self.program_id);
-            assign_fn(uniform_location, value);
-            let error = gl::GetError();
-            gl::UseProgram(active_program as GLuint);
-
-            if error != gl::NO_ERROR {
-                return Err(ShaderProgramError::GlUniform(GlUniformError::new(error)));
-            }
-        }
-
         Ok(())
     }
 
-    pub fn set_uniform_matrix<T>(&self, name: &str, value: T, assign_fn: unsafe fn(GLint, GLsizei, GLboolean, T) -> ()) -> Result<(), ShaderProgramError> {
-        let uniform_location: i32 = match self.uniforms.get(name) {
-            Some(u) => *u,
-            None => return Err(ShaderProgramError::UniformNotFound)
-        };
+    // Set a located uniform, e.g. set_uniform("elapsed", 1.5) or set_uniform("projection", matrix).
+    // The program doesn't have to be in use, whatever is in use is restored afterwards
+    pub fn set_uniform(&self, name: &str, value: impl Into<Uniform>) -> Result<()> {
+        let uniform_location = self.uniform_location(name)?;
 
-        unsafe {
-            let mut active_program: GLint = 0;
-            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut active_program);
+        let active_program = self.backend.get_integer(gl::CURRENT_PROGRAM);
+        self.backend.use_program(self.program_id);
+        self.backend.uniform(uniform_location, value.into());
+        let error = self.backend.check_error("glUniform");
+        self.backend.use_program(active_program as GLuint);
 
-            gl::UseProgram(self.program_id);
-            assign_fn(uniform_location, 1, gl::FALSE, value);
-            let error = gl::GetError();
-            gl::UseProgram(active_program as GLuint);
-
-            if error != gl::NO_ERROR {
-                return Err(ShaderProgramError::GlUniform(GlUniformError::new(error)));
-            }
-        }
+        error.map_err(|source| uniform_error(name, source))
+    }
 
-        Ok(())
+    fn uniform_location(&self, name: &str) -> Result<GLint> {
+        self.uniforms.get(name)
+            .copied()
+            .ok_or_else(|| ProgramError::UniformNotFound(name.to_string()).into())
     }
-   
+}
+
+fn uniform_error(name: &str, source: GlError) -> Error {
+    ProgramError::Uniform { name: name.to_string(), source }.into()
 }
 
 
 pub struct ProgramBuilder {
     program_id: u32,
     shaders: Vec::<u32>,
+    // File names of the shaders, the program is labeled after them
+    shader_names: Vec<String>,
+    backend: Rc<dyn GlBackend>,
 }
 
 impl ProgramBuilder {
+    // Not Default, since this creates a GL object and needs a current context
+    #[allow(clippy::new_without_default)]
     pub fn new() -> ProgramBuilder {
-        let program_id = unsafe {
-            gl::CreateProgram()
-        };
+        let backend = backend::current();
+        let program_id = backend.create_program();
 
         ProgramBuilder {
             program_id,
             shaders: vec![],
+            shader_names: vec![],
+            backend,
         }
     }
 
-    pub fn attach_file(self, shader_path: &str) -> ProgramBuilder {
+    pub fn attach_file(mut self, shader_path: &str) -> Result<ProgramBuilder> {
         let path = Path::new(shader_path);
-        if let Some(extension) = path.extension() {
-            let shader_type = ShaderType::from_ext(extension)
-                .expect("Failed to parse file extension.");
-            let shader_src = std::fs::read_to_string(path)
-                .expect(&format!("Failed to read shader source. {}", shader_path));
-
-            self.compile_shader(&shader_src, shader_type)
-        } else {
-            panic!("Failed to read extension of file with path: {}", shader_path);
-        }
+        let file_name = path.file_name().map_or_else(|| shader_path.into(), |name| name.to_string_lossy());
+        self.shader_names.push(file_name.into_owned());
+        let shader_type = path.extension()
+            .and_then(ShaderType::from_ext)
+            .ok_or_else(|| ShaderError::UnknownType(shader_path.to_string()))?;
+        let shader_src = std::fs::read_to_string(path).context(shader_path)?;
+
+        self.compile_named_shader(shader_path, &shader_src, shader_type)
     }
 
-    pub fn compile_shader(mut self, shader_src: &str, shader_type: ShaderType) -> ProgramBuilder {
-        let shader = unsafe {
-            let shader = gl::CreateShader(shader_type.into());
-            let c_str_shader = CString::new(shader_src.as_bytes()).unwrap();
-
-            gl::ShaderSource(shader, 1, &c_str_shader.as_ptr(), ptr::null());
-            gl::CompileShader(shader);
-            if !self.check_shader_errors(shader) {
-                panic!("Shader failed to compile.");
-            }
+    pub fn compile_shader(self, shader_src: &str, shader_type: ShaderType) -> Result<ProgramBuilder> {
+        self.compile_named_shader("shader source", shader_src, shader_type)
+    }
 
-            shader
-        };
+    // The name is used in error messages and as the label of the shader
+    fn compile_named_shader(mut self, name: &str, shader_src: &str, shader_type: ShaderType) -> Result<ProgramBuilder> {
+        let _span = profiling::span("shader compile");
+        let c_str_shader = CString::new(shader_src.as_bytes()).map_err(ShaderError::Nul)?;
+        let shader = self.backend.create_shader(shader_type.into());
+        self.backend.shader_source(shader, &c_str_shader);
+        self.backend.compile_shader(shader);
+        debug::label(self.backend.as_ref(), gl::SHADER, shader, name);
+
+        if let Some(log) = self.shader_errors(shader) {
+            debug::forget_label(gl::SHADER, shader);
+            self.backend.delete_shader(shader);
+            return Err(ShaderError::Compile { name: name.to_string(), log }.into());
+        }
 
         self.shaders.push(shader);
 
-        self
+        Ok(self)
     }
 
-    unsafe fn check_shader_errors(&self, shader_id: u32) -> bool {
-        let mut success = i32::from(gl::FALSE);
-        let mut info_log = Vec::with_capacity(512);
-
-        info_log.set_len(512 - 1);
-        gl::GetShaderiv(shader_id, gl::COMPILE_STATUS, &mut success);
-        if success != i32::from(gl::TRUE) {
-            gl::GetShaderInfoLog(
-                shader_id,
-                512,
-                ptr::null_mut(),
-                info_log.as_mut_ptr() as *mut gl::types::GLchar,
-            );
-            println!("ERROR::Shader Compilation Failed!\n{}", String::from_utf8_lossy(&info_log));
-            return false;
+    fn shader_errors(&self, shader_id: u32) -> Option<String> {
+        if self.backend.get_shader_iv(shader_id, gl::COMPILE_STATUS) != GLint::from(gl::TRUE) {
+            return Some(self.backend.shader_info_log(shader_id));
         }
 
-        true
+        None
     }
 
-    unsafe fn check_linker_errors(&self) -> bool {
-        let mut success = i32::from(gl::FALSE);
-        let mut info_log = Vec::with_capacity(512);
-
-        info_log.set_len(512 - 1);
-        gl::GetProgramiv(self.program_id, gl::LINK_STATUS, &mut success);
-        if success != i32::from(gl::TRUE) {
-            gl::GetProgramInfoLog(
-                self.program_id,
-                512,
-                ptr::null_mut(),
-                info_log.as_mut_ptr() as *mut gl::types::GLchar,
-            );
-            println!("ERROR::SHADER::PROGRAM::COMPILATION_FAILED\n{}", String::from_utf8_lossy(&info_log));
-            return false;
+    fn linker_errors(&self) -> Option<String> {
+        if self.backend.get_program_iv(self.program_id, gl::LINK_STATUS) != GLint::from(gl::TRUE) {
+            return Some(self.backend.program_info_log(self.program_id));
         }
-        true
+
+        None
     }
 
-    pub fn link(self) -> Program {
-        unsafe {
-            for &shader in &self.shaders {
-                gl::AttachShader(self.program_id, shader);
-            }
-            gl::LinkProgram(self.program_id);
-    
-            // todo:: use this to make safer abstraction
-            self.check_linker_errors();
-    
-            for &shader in &self.shaders {
-                gl::DeleteShader(shader);
-            }
+    pub fn link(mut self) -> Result<Program> {
+        let _span = profiling::span("program link");
+        for &shader in &self.shaders {
+            self.backend.attach_shader(self.program_id, shader);
+        }
+        self.backend.link_program(self.program_id);
+        if !self.shader_names.is_empty() {
+            debug::label(self.backend.as_ref(), gl::PROGRAM, self.program_id, &self.shader_names.join(" + "));
+        }
+        let link_errors = self.linker_errors();
+
+        // On failure, dropping the builder cleans up the program and shaders
+        if let Some(log) = link_errors {
+            return Err(ProgramError::Link { log }.into());
+        }
+
+        // The shaders are flagged for deletion and go away with the program
+        for shader in self.shaders.drain(..) {
+            debug::forget_label(gl::SHADER, shader);
+            self.backend.delete_shader(shader);
         }
 
-        Program {
-            program_id: self.program_id,
-            uniforms: HashMap::new()
+        Ok(Program {
+            program_id: std::mem::replace(&mut self.program_id, 0),
+            uniforms: HashMap::new(),
+            backend: Rc::clone(&self.backend),
+        })
+    }
+}
+
+// Only owns anything if the builder was dropped before linking, or linking failed
+impl Drop for ProgramBuilder {
+    fn drop(&mut self) {
+        for &shader in &self.shaders {
+            debug::forget_label(gl::SHADER, shader);
+            self.backend.delete_shader(shader);
+        }
+        // Linking hands the program over and leaves 0 behind
+        if self.program_id != 0 {
+            debug::forget_label(gl::PROGRAM, self.program_id);
+            self.backend.delete_program(self.program_id);
         }
     }
 }
\ No newline at end of file
@@ -1,4 +1,3 @@
-#[allow(dead_code)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -8,21 +7,21 @@ pub enum ShaderType {
 }
 
 impl ShaderType {
-    pub fn from_ext(ext: &std::ffi::OsStr) -> Result<ShaderType, String> {
-        match ext.to_str().expect("Failed to read extension") {
-            "vert" => { Ok(ShaderType::Vertex) },
-            "frag" => { Ok(ShaderType::Fragment) },
-            "tcs"  => { Ok(ShaderType::TessellationControl) },
-            "tes"  => { Ok(ShaderType::TessellationEvaluation) },
-            "geom" => { Ok(ShaderType::Geometry) },
-            e => { Err(e.to_string()) },
+    pub fn from_ext(ext: &std::ffi::OsStr) -> Option<ShaderType> {
+        match ext.to_str()? {
+            "vert" => { Some(ShaderType::Vertex) },
+            "frag" => { Some(ShaderType::Fragment) },
+            "tcs"  => { Some(ShaderType::TessellationControl) },
+            "tes"  => { Some(ShaderType::TessellationEvaluation) },
+            "geom" => { Some(ShaderType::Geometry) },
+            _ => { None },
         }
     }
 }
 
-impl Into<gl::types::GLenum> for ShaderType {
-    fn into(self) -> gl::types::GLenum {
-        match self {
+impl From<ShaderType> for gl::types::GLenum {
+    fn from(shader_type: ShaderType) -> gl::types::GLenum {
+        match shader_type {
             ShaderType::Vertex                  => { gl::VERTEX_SHADER          },
             ShaderType::Fragment                => { gl::FRAGMENT_SHADER        },
             ShaderType::TessellationControl     => { gl::TESS_CONTROL_SHADER    },
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+// A value that can be assigned to a uniform. Everything that converts into one can be passed to
+// Program::set_uniform directly
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "UniformData", into = "UniformData")]
+pub enum Uniform {
+    Float(f32),
+    Int(i32),
+    UInt(u32),
+    Vec2(glm::Vec2),
+    Vec3(glm::Vec3),
+    Vec4(glm::Vec4),
+    Mat3(glm::Mat3),
+    Mat4(glm::Mat4),
+}
+
+impl From<f32> for Uniform {
+    fn from(value: f32) -> Uniform { Uniform::Float(value) }
+}
+
+impl From<i32> for Uniform {
+    fn from(value: i32) -> Uniform { Uniform::Int(value) }
+}
+
+impl From<u32> for Uniform {
+    fn from(value: u32) -> Uniform { Uniform::UInt(value) }
+}
+
+impl From<glm::Vec2> for Uniform {
+    fn from(value: glm::Vec2) -> Uniform { Uniform::Vec2(value) }
+}
+
+impl From<glm::Vec3> for Uniform {
+    fn from(value: glm::Vec3) -> Uniform { Uniform::Vec3(value) }
+}
+
+impl From<glm::Vec4> for Uniform {
+    fn from(value: glm::Vec4) -> Uniform { Uniform::Vec4(value) }
+}
+
+impl From<glm::Mat3> for Uniform {
+    fn from(value: glm::Mat3) -> Uniform { Uniform::Mat3(value) }
+}
+
+impl From<glm::Mat4> for Uniform {
+    fn from(value: glm::Mat4) -> Uniform { Uniform::Mat4(value) }
+}
+
+// How uniforms are stored in traces, since the glm types can't be serialized as they are
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum UniformData {
+    Float(f32),
+    Int(i32),
+    UInt(u32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat3([f32; 9]),
+    Mat4([f32; 16]),
+}
+
+impl From<Uniform> for UniformData {
+    fn from(value: Uniform) -> UniformData {
+        match value {
+            Uniform::Float(v) => UniformData::Float(v),
+            Uniform::Int(v) => UniformData::Int(v),
+            Uniform::UInt(v) => UniformData::UInt(v),
+            Uniform::Vec2(v) => UniformData::Vec2([v.x, v.y]),
+            Uniform::Vec3(v) => UniformData::Vec3([v.x, v.y, v.z]),
+            Uniform::Vec4(v) => UniformData::Vec4([v.x, v.y, v.z, v.w]),
+            Uniform::Mat3(v) => {
+                let mut data = [0.0; 9];
+                data.copy_from_slice(v.as_slice());
+                UniformData::Mat3(data)
+            },
+            Uniform::Mat4(v) => {
+                let mut data = [0.0; 16];
+                data.copy_from_slice(v.as_slice());
+                UniformData::Mat4(data)
+            },
+        }
+    }
+}
+
+impl From<UniformData> for Uniform {
+    fn from(data: UniformData) -> Uniform {
+        match data {
+            UniformData::Float(v) => Uniform::Float(v),
+            UniformData::Int(v) => Uniform::Int(v),
+            UniformData::UInt(v) => Uniform::UInt(v),
+            UniformData::Vec2(v) => Uniform::Vec2(glm::make_vec2(&v)),
+            UniformData::Vec3(v) => Uniform::Vec3(glm::make_vec3(&v)),
+            UniformData::Vec4(v) => Uniform::Vec4(glm::make_vec4(&v)),
+            UniformData::Mat3(v) => Uniform::Mat3(glm::make_mat3(&v)),
+            UniformData::Mat4(v) => Uniform::Mat4(glm::make_mat4(&v)),
+        }
+    }
+}
@@ -1,101 +1,129 @@
+use std::rc::Rc;
+
 use gl;
 use gl::types::{GLuint, GLsizei};
 
+use crate::error::{Error, Result};
+use crate::profiling;
+
 use super::{
     helpers,
-    bindable::Bindable
+    debug,
+    bindable::Bindable,
+    backend::{self, GlBackend}
 };
 
 pub struct Triangle {
     id: GLuint,
     b_ids: [GLuint; 2],
-    pub count: GLsizei
+    pub count: GLsizei,
+    backend: Rc<dyn GlBackend>,
 }
 
 
 impl Drop for Triangle {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(2, self.b_ids.as_ptr());
-            gl::DeleteVertexArrays(1, self.id as *const u32);
+        for &b_id in &self.b_ids {
+            debug::forget_label(gl::BUFFER, b_id);
         }
+        debug::forget_label(gl::VERTEX_ARRAY, self.id);
+        self.backend.delete_buffers(&self.b_ids);
+        self.backend.delete_vertex_arrays(&[self.id]);
     }
 }
 
 impl Bindable for Triangle {
     fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.id);
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.b_ids[Triangle::VERT_INDX]);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.b_ids[Triangle::INDC_INDX]);
-        }
+        self.backend.bind_vertex_array(self.id);
+        self.backend.bind_buffer(gl::ARRAY_BUFFER, self.b_ids[Triangle::VERT_INDX]);
+        self.backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.b_ids[Triangle::INDC_INDX]);
     }
 
     fn unbind(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
-        }
+        self.backend.bind_buffer(gl::ARRAY_BUFFER, 0);
+        self.backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+        self.backend.bind_vertex_array(0);
     }
 }
 
-// TODO: errors
 impl Triangle {
     pub const VERT_INDX: usize = 0;
     pub const INDC_INDX: usize = 1;
 
-    pub fn init(vertices: &Vec<f32>, indices: &Vec<u32>) -> Triangle  {
+    // Vertices are tightly packed xyz positions, and every index has to point at one of them
+    pub fn init(vertices: &[f32], indices: &[u32]) -> Result<Triangle> {
+        let _span = profiling::span("mesh upload");
+        if vertices.is_empty() || indices.is_empty() {
+            return Err(Error::Buffer("a mesh needs at least one vertex and index".to_string()));
+        }
+        if !vertices.len().is_multiple_of(3) {
+            return Err(Error::Buffer(format!("vertex data has {} floats, which is not a multiple of 3", vertices.len())));
+        }
+        let vertex_count = vertices.len() / 3;
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertex_count) {
+            return Err(Error::Buffer(format!("index {} is out of range for {} vertices", index, vertex_count)));
+        }
+
+        let backend = backend::current();
         let mut id: GLuint = 0;
         let mut b_ids: [GLuint; 2] = [0; 2];
 
-        unsafe {
-            gl::GenVertexArrays(1, &mut id);
-            gl::BindVertexArray(id);
-
-            gl::GenBuffers(2, b_ids.as_mut_ptr());
-
-            // instantiate vertices buffer
-            gl::BindBuffer(gl::ARRAY_BUFFER, b_ids[Triangle::VERT_INDX]);
-            gl::BufferData(
-                gl::ARRAY_BUFFER, 
-                helpers::byte_size_of_array(vertices),
-                helpers::array_to_c_void(vertices),
-                gl::STATIC_DRAW
-            );
-            
-            // instantiate indices buffer
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, b_ids[Triangle::INDC_INDX]);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER, 
-                helpers::byte_size_of_array(indices),
-                helpers::array_to_c_void(indices),
-                gl::STATIC_DRAW
-            );
-            
-            // Vertex attributes
-            gl::EnableVertexAttribArray(0);
-            let components = 3;
-            let stride = components * helpers::size_of::<f32>();
-            gl::VertexAttribPointer(
-                0,                      // index of the generic vertex attribute ("layout (location = 0)")
-                components,             // the number of components per generic vertex attribute
-                gl::FLOAT,              // data type
-                gl::FALSE,              // normalized (int-to-float conversion)
-                stride,                 // stride (byte offset between consecutive attributes)
-                std::ptr::null()        // offset of the first component
-            );
-            
-            // Better safe than sorry :) 
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
-        }
+        backend.gen_vertex_arrays(std::slice::from_mut(&mut id));
+        backend.bind_vertex_array(id);
+
+        backend.gen_buffers(&mut b_ids);
+
+        // instantiate vertices buffer
+        backend.bind_buffer(gl::ARRAY_BUFFER, b_ids[Triangle::VERT_INDX]);
+        backend.buffer_data(gl::ARRAY_BUFFER, helpers::as_bytes(vertices), gl::STATIC_DRAW);
 
-        Triangle {
+        // instantiate indices buffer
+        backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, b_ids[Triangle::INDC_INDX]);
+        backend.buffer_data(gl::ELEMENT_ARRAY_BUFFER, helpers::as_bytes(indices), gl::STATIC_DRAW);
+
+        // Vertex attributes
+        backend.enable_vertex_attrib_array(0);
+        let components = 3;
+        let stride = components * helpers::size_of::<f32>();
+        backend.vertex_attrib_pointer(
+            0,                      // index of the generic vertex attribute ("layout (location = 0)")
+            components,             // the number of components per generic vertex attribute
+            gl::FLOAT,              // data type
+            false,                  // normalized (int-to-float conversion)
+            stride,                 // stride (byte offset between consecutive attributes)
+            0                       // offset of the first component
+        );
+
+        // Better safe than sorry :) 
+        backend.bind_buffer(gl::ARRAY_BUFFER, 0);
+        backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+        backend.bind_vertex_array(0);
+
+        // Wrapped first, so the buffers are deleted if uploading failed
+        let triangle = Triangle {
             id,
             b_ids,
-            count: indices.len() as i32
-        }
+            count: indices.len() as i32,
+            backend: Rc::clone(&backend),
+        };
+        backend.check_error("Triangle::init")?;
+        triangle.set_label(&format!("mesh of {} triangles", indices.len() / 3));
+
+        Ok(triangle)
     }
-}
\ No newline at end of file
+
+    // Name the vertex array and its buffers in debuggers and debug messages
+    pub fn set_label(&self, name: &str) {
+        debug::label(self.backend.as_ref(), gl::VERTEX_ARRAY, self.id, name);
+        debug::label(self.backend.as_ref(), gl::BUFFER, self.b_ids[Triangle::VERT_INDX], &format!("{} vertices", name));
+        debug::label(self.backend.as_ref(), gl::BUFFER, self.b_ids[Triangle::INDC_INDX], &format!("{} indices", name));
+    }
+
+    // Draw every index as triangles, with the program that is in use
+    pub fn draw(&self) {
+        let _span = profiling::span("draw submission");
+        self.bind();
+        self.backend.draw_elements(gl::TRIANGLES, self.count, gl::UNSIGNED_INT, 0);
+        self.unbind();
+    }
+}
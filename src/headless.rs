@@ -0,0 +1,130 @@
+use std::ffi::c_void;
+
+use crate::error::Result;
+
+// An OpenGL context without a window, for rendering on machines without a display. Draws into a
+// Framebuffer instead of a default framebuffer, since there is none.
+//
+// On Linux this uses EGL on Mesa's surfaceless platform, which works without X11, Wayland or a
+// GPU through the llvmpipe software rasterizer. Other platforms go through glutin, which still
+// needs a display but never shows a window.
+pub struct HeadlessContext {
+    backend: Backend,
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    Egl(egl::EglContext),
+    #[cfg(not(target_os = "linux"))]
+    Glutin {
+        context: glutin::Context<glutin::PossiblyCurrent>,
+        // The context is only valid while its event loop is alive
+        _event_loop: glutin::event_loop::EventLoop<()>,
+    },
+}
+
+impl HeadlessContext {
+    // Create a context and make it current on this thread, with the GL functions loaded
+    pub fn new(debug: bool) -> Result<HeadlessContext> {
+        #[cfg(target_os = "linux")]
+        let backend = Backend::Egl(egl::EglContext::new(debug)?);
+
+        #[cfg(not(target_os = "linux"))]
+        let backend = {
+            let event_loop = glutin::event_loop::EventLoop::new();
+            let context = glutin::ContextBuilder::new()
+                .with_gl_debug_flag(debug)
+                .build_headless(&event_loop, glutin::dpi::PhysicalSize::new(1, 1))
+                .map_err(|e| crate::error::Error::Context(e.to_string()))?;
+            let context = unsafe { context.make_current() }
+                .map_err(|(_, e)| crate::error::Error::Context(e.to_string()))?;
+            Backend::Glutin { context, _event_loop: event_loop }
+        };
+
+        let context = HeadlessContext { backend };
+        gl::load_with(|symbol| context.get_proc_address(symbol));
+        Ok(context)
+    }
+
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        match &self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Egl(context) => context.get_proc_address(symbol),
+            #[cfg(not(target_os = "linux"))]
+            Backend::Glutin { context, .. } => context.get_proc_address(symbol) as *const c_void,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod egl {
+    use std::ffi::c_void;
+
+    use khronos_egl as egl;
+
+    use crate::error::{Error, Result};
+
+    // From EGL_MESA_platform_surfaceless, which khronos-egl doesn't have a constant for
+    const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+    pub struct EglContext {
+        egl: egl::DynamicInstance<egl::EGL1_5>,
+        display: egl::Display,
+        context: egl::Context,
+    }
+
+    impl EglContext {
+        pub fn new(debug: bool) -> Result<EglContext> {
+            let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+                .map_err(|e| Error::Context(format!("failed to load libEGL: {}", e)))?;
+            let error = |what: &str, e: egl::Error| Error::Context(format!("{} failed: {}", what, e));
+
+            // Fall back to the default display, which works on a desktop that has one
+            let display = unsafe { egl.get_platform_display(PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE]) }
+                .ok()
+                .or_else(|| unsafe { egl.get_display(egl::DEFAULT_DISPLAY) })
+                .ok_or_else(|| Error::Context("no EGL display available".to_string()))?;
+            egl.initialize(display).map_err(|e| error("eglInitialize", e))?;
+
+            // Without a surface, the config only has to be able to render OpenGL
+            let config_attributes = [
+                egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+                egl::SURFACE_TYPE, 0,
+                egl::NONE,
+            ];
+            let config = egl.choose_first_config(display, &config_attributes)
+                .map_err(|e| error("eglChooseConfig", e))?
+                .ok_or_else(|| Error::Context("no EGL config supports OpenGL".to_string()))?;
+
+            egl.bind_api(egl::OPENGL_API).map_err(|e| error("eglBindAPI", e))?;
+            let context_attributes = [
+                egl::CONTEXT_MAJOR_VERSION, 3,
+                egl::CONTEXT_MINOR_VERSION, 3,
+                egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                egl::CONTEXT_OPENGL_DEBUG, if debug { egl::TRUE as egl::Int } else { egl::FALSE as egl::Int },
+                egl::NONE,
+            ];
+            let context = egl.create_context(display, config, None, &context_attributes)
+                .map_err(|e| error("eglCreateContext", e))?;
+            // Needs EGL_KHR_surfaceless_context, which Mesa always has
+            egl.make_current(display, None, None, Some(context))
+                .map_err(|e| error("eglMakeCurrent", e))?;
+
+            Ok(EglContext { egl, display, context })
+        }
+
+        pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+            self.egl.get_proc_address(symbol)
+                .map_or(std::ptr::null(), |address| address as *const c_void)
+        }
+    }
+
+    impl Drop for EglContext {
+        fn drop(&mut self) {
+            // Errors here can't be handled, and the process is usually about to exit anyway
+            let _ = self.egl.make_current(self.display, None, None, None);
+            let _ = self.egl.destroy_context(self.display, self.context);
+            let _ = self.egl.terminate(self.display);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use super::{
+    bindings::{Binding, Bindings},
+    state::InputState
+};
+
+// Looks up named actions and axes in the current input, so game code doesn't deal with raw keys
+pub struct Actions<'a> {
+    pub input: &'a InputState,
+    pub bindings: &'a Bindings,
+}
+
+impl<'a> Actions<'a> {
+    pub fn new(input: &'a InputState, bindings: &'a Bindings) -> Actions<'a> {
+        Actions { input, bindings }
+    }
+
+    // True while any binding of the action is held
+    pub fn is_down(&self, action: &str) -> bool {
+        self.bindings.action(action).iter().any(|&b| self.binding_down(b))
+    }
+
+    // True on the frame any binding of the action was pressed
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.bindings.action(action).iter().any(|&b| match b {
+            Binding::Key(key) => self.input.is_key_pressed(key),
+            Binding::Mouse(button) => self.input.is_button_pressed(button),
+        })
+    }
+
+    // -1, 0 or 1. Holding both directions cancels out
+    pub fn axis(&self, axis: &str) -> f32 {
+        let axis = match self.bindings.axis(axis) {
+            Some(axis) => axis,
+            None => return 0.0,
+        };
+
+        let positive = axis.positive.iter().any(|&b| self.binding_down(b));
+        let negative = axis.negative.iter().any(|&b| self.binding_down(b));
+        (positive as i32 - negative as i32) as f32
+    }
+
+    fn binding_down(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.input.is_key_down(key),
+            Binding::Mouse(button) => self.input.is_button_down(button),
+        }
+    }
+}
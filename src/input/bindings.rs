@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io,
+    path::Path,
+    str::FromStr
+};
+
+use glutin::event::{MouseButton, VirtualKeyCode};
+use serde::{Deserialize, de::IntoDeserializer};
+
+// Something physical that can trigger an action
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl FromStr for Binding {
+    type Err = BindingsError;
+
+    // Keys use the glutin VirtualKeyCode names ("W", "Escape", "LShift", ...)
+    // and mouse buttons are "MouseLeft", "MouseRight", "MouseMiddle" or "Mouse<number>"
+    fn from_str(name: &str) -> Result<Binding, BindingsError> {
+        let unknown = || BindingsError::UnknownBinding(name.to_string());
+
+        if let Some(button) = name.strip_prefix("Mouse") {
+            let button = match button {
+                "Left" => MouseButton::Left,
+                "Right" => MouseButton::Right,
+                "Middle" => MouseButton::Middle,
+                n => MouseButton::Other(n.parse().map_err(|_| unknown())?),
+            };
+            return Ok(Binding::Mouse(button));
+        }
+
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> = name.into_deserializer();
+        VirtualKeyCode::deserialize(deserializer)
+            .map(Binding::Key)
+            .map_err(|_| unknown())
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(MouseButton::Other(n)) => write!(f, "Mouse{}", n),
+            Binding::Mouse(button) => write!(f, "Mouse{:?}", button),
+        }
+    }
+}
+
+// An axis goes from -1 to 1 depending on which of its bindings are held
+#[derive(Clone, Debug, Default)]
+pub struct Axis {
+    pub positive: Vec<Binding>,
+    pub negative: Vec<Binding>,
+}
+
+// Maps named actions and axes to bindings. The defaults are compiled in and any entry found in a
+// bindings file replaces the default for that name, so a file only needs to contain what it changes
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    actions: HashMap<String, Vec<Binding>>,
+    axes: HashMap<String, Axis>,
+}
+
+pub const DEFAULT_BINDINGS_PATH: &str = "assets/config/bindings.toml";
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        use VirtualKeyCode::*;
+        let key = |keys: &[VirtualKeyCode]| keys.iter().map(|&k| Binding::Key(k)).collect::<Vec<_>>();
+
+        let mut bindings = Bindings { actions: HashMap::new(), axes: HashMap::new() };
+        bindings.bind_action("quit", key(&[Escape]));
+        bindings.bind_action("toggle_wireframe", key(&[T]));
+        bindings.bind_action("toggle_camera", key(&[Tab]));
+        bindings.bind_action("frame_object", key(&[F]));
+        bindings.bind_action("cycle_window_mode", key(&[F11]));
+        bindings.bind_action("toggle_vsync", key(&[V]));
+        bindings.bind_action("screenshot", key(&[F12]));
+        bindings.bind_action("orbit_rotate", vec![Binding::Mouse(MouseButton::Left)]);
+        bindings.bind_action("orbit_pan", key(&[LShift, RShift]));
+
+        bindings.bind_axis("move_forward", key(&[W]), key(&[S]));
+        bindings.bind_axis("move_right", key(&[D]), key(&[A]));
+        bindings.bind_axis("move_up", key(&[E]), key(&[Q]));
+        bindings.bind_axis("camera_speed", key(&[Equals, Add]), key(&[Minus, Subtract]));
+        bindings.bind_axis("camera_fov", key(&[X]), key(&[Z]));
+        bindings
+    }
+}
+
+// Layout of the bindings file, see assets/config/bindings.toml
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingsFile {
+    #[serde(default)]
+    actions: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    axes: HashMap<String, AxisFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AxisFile {
+    #[serde(default)]
+    positive: Vec<String>,
+    #[serde(default)]
+    negative: Vec<String>,
+}
+
+fn parse_bindings(names: &[String]) -> Result<Vec<Binding>, BindingsError> {
+    names.iter().map(|name| name.parse()).collect()
+}
+
+impl Bindings {
+    // Load the defaults overridden by the given file. A missing file just gives the defaults
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bindings, BindingsError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(src) => Bindings::from_toml(&src),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Bindings::default()),
+            Err(e) => Err(BindingsError::Io(path.display().to_string(), e)),
+        }
+    }
+
+    pub fn from_toml(src: &str) -> Result<Bindings, BindingsError> {
+        let file: BindingsFile = toml::from_str(src).map_err(BindingsError::Parse)?;
+
+        let mut bindings = Bindings::default();
+        for (name, names) in file.actions.iter() {
+            bindings.bind_action(name, parse_bindings(names)?);
+        }
+        for (name, axis) in file.axes.iter() {
+            bindings.bind_axis(name, parse_bindings(&axis.positive)?, parse_bindings(&axis.negative)?);
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn bind_action(&mut self, action: &str, bindings: Vec<Binding>) {
+        self.actions.insert(action.to_string(), bindings);
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, positive: Vec<Binding>, negative: Vec<Binding>) {
+        self.axes.insert(axis.to_string(), Axis { positive, negative });
+    }
+
+    // Unknown names have no bindings, which makes them never trigger
+    pub fn action(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn axis(&self, axis: &str) -> Option<&Axis> {
+        self.axes.get(axis)
+    }
+
+    pub fn is_bound(&self, action: &str, binding: Binding) -> bool {
+        self.action(action).contains(&binding)
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(String, io::Error),
+    Parse(toml::de::Error),
+    UnknownBinding(String),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingsError::Io(path, _) => write!(f, "Failed to read bindings file {}", path),
+            BindingsError::Parse(_) => write!(f, "Failed to parse bindings file"),
+            BindingsError::UnknownBinding(name) => write!(f, "Unknown key or mouse button \"{}\" in bindings", name),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BindingsError::Io(_, e) => Some(e),
+            BindingsError::Parse(e) => Some(e),
+            BindingsError::UnknownBinding(_) => None,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use glutin::event::{
+    WindowEvent, DeviceEvent, KeyboardInput, ElementState, MouseButton, MouseScrollDelta,
+    ModifiersState, VirtualKeyCode
+};
+
+// Pixels per line when converting touchpad scrolling to mouse wheel lines
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+// Owned, thread safe subset of the glutin events that the render thread cares about.
+// The event loop converts and sends these over a channel, see InputState
+#[derive(Clone, Copy, Debug)]
+pub enum InputEvent {
+    Key { key: VirtualKeyCode, state: ElementState },
+    MouseButton { button: MouseButton, state: ElementState },
+    // Cursor position in physical pixels relative to the top left corner of the window
+    CursorMoved { x: f32, y: f32 },
+    // Raw mouse movement, not affected by cursor acceleration or the cursor being grabbed
+    MouseMotion { delta_x: f32, delta_y: f32 },
+    // Scroll in lines, positive y is away from the user
+    Scroll { x: f32, y: f32 },
+    Modifiers(ModifiersState),
+    Focused(bool),
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<InputEvent> {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state, virtual_keycode: Some(key), .. }, ..
+            } => Some(InputEvent::Key { key: *key, state: *state }),
+            WindowEvent::MouseInput { state, button, .. } => {
+                Some(InputEvent::MouseButton { button: *button, state: *state })
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                Some(InputEvent::CursorMoved { x: position.x as f32, y: position.y as f32 })
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(position) => (
+                        position.x as f32 / PIXELS_PER_SCROLL_LINE,
+                        position.y as f32 / PIXELS_PER_SCROLL_LINE
+                    ),
+                };
+                Some(InputEvent::Scroll { x, y })
+            },
+            WindowEvent::ModifiersChanged(modifiers) => Some(InputEvent::Modifiers(*modifiers)),
+            WindowEvent::Focused(focused) => Some(InputEvent::Focused(*focused)),
+            _ => None,
+        }
+    }
+
+    pub fn from_device_event(event: &DeviceEvent) -> Option<InputEvent> {
+        match event {
+            DeviceEvent::MouseMotion { delta } => {
+                Some(InputEvent::MouseMotion { delta_x: delta.0 as f32, delta_y: delta.1 as f32 })
+            },
+            _ => None,
+        }
+    }
+}
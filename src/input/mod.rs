@@ -0,0 +1,4 @@
+pub mod actions;
+pub mod bindings;
+pub mod event;
+pub mod state;
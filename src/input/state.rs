@@ -0,0 +1,137 @@
+use glutin::event::{ElementState, MouseButton, ModifiersState, VirtualKeyCode};
+
+use super::event::InputEvent;
+
+// Snapshot of the input for a single frame, built by the render thread from the InputEvents the
+// event loop sends it. The channel lets the threads hand over input without fighting over a lock.
+// "Pressed" and "released" are only true for the frame where the change happened,
+// while "down" is true for as long as the key or button is held.
+#[derive(Default)]
+pub struct InputState {
+    keys_down: Vec<VirtualKeyCode>,
+    keys_pressed: Vec<VirtualKeyCode>,
+    keys_released: Vec<VirtualKeyCode>,
+
+    buttons_down: Vec<MouseButton>,
+    buttons_pressed: Vec<MouseButton>,
+    buttons_released: Vec<MouseButton>,
+
+    cursor_position: (f32, f32),
+    mouse_delta: (f32, f32),
+    scroll: (f32, f32),
+    modifiers: ModifiersState,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    // Forget everything that only lasts for a single frame. Call this before processing the
+    // events that arrived since the last frame
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = (0.0, 0.0);
+    }
+
+    pub fn process(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Key { key, state } => {
+                update_element(key, state, &mut self.keys_down, &mut self.keys_pressed, &mut self.keys_released);
+            },
+            InputEvent::MouseButton { button, state } => {
+                update_element(button, state, &mut self.buttons_down, &mut self.buttons_pressed, &mut self.buttons_released);
+            },
+            InputEvent::CursorMoved { x, y } => {
+                self.cursor_position = (x, y);
+            },
+            InputEvent::MouseMotion { delta_x, delta_y } => {
+                self.mouse_delta.0 += delta_x;
+                self.mouse_delta.1 += delta_y;
+            },
+            InputEvent::Scroll { x, y } => {
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            },
+            InputEvent::Modifiers(modifiers) => {
+                self.modifiers = modifiers;
+            },
+            // We won't be told about keys released while the window is out of focus,
+            // so release everything now instead of leaving keys stuck
+            InputEvent::Focused(false) => {
+                self.keys_released.append(&mut self.keys_down);
+                self.buttons_released.append(&mut self.buttons_down);
+                self.modifiers = ModifiersState::empty();
+            },
+            InputEvent::Focused(true) => { },
+        }
+    }
+
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn is_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn is_key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    // Cursor position in physical pixels relative to the top left corner of the window
+    pub fn cursor_position(&self) -> (f32, f32) {
+        self.cursor_position
+    }
+
+    // Raw mouse movement since the last frame
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta
+    }
+
+    // Scroll in lines since the last frame, positive y is away from the user
+    pub fn scroll(&self) -> (f32, f32) {
+        self.scroll
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+}
+
+// Shared bookkeeping for keys and mouse buttons. Repeated presses from holding a key down are ignored
+fn update_element<T: PartialEq + Copy>(
+    element: T, state: ElementState,
+    down: &mut Vec<T>, pressed: &mut Vec<T>, released: &mut Vec<T>
+) {
+    match state {
+        ElementState::Pressed => {
+            if !down.contains(&element) {
+                down.push(element);
+                pressed.push(element);
+            }
+        },
+        ElementState::Released => {
+            if let Some(i) = down.iter().position(|&e| e == element) {
+                down.remove(i);
+                released.push(element);
+            }
+        }
+    }
+}
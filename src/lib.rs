@@ -0,0 +1,21 @@
+pub extern crate nalgebra_glm as glm;
+pub extern crate gl;
+pub extern crate glutin;
+
+pub mod gl_utils;
+pub mod scene;
+pub mod camera;
+pub mod input;
+pub mod events;
+pub mod display;
+pub mod config;
+pub mod app;
+pub mod runner;
+pub mod crash;
+pub mod error;
+pub mod headless;
+pub mod screenshot;
+pub mod recording;
+pub mod trace;
+pub mod stats;
+pub mod profiling;
@@ -0,0 +1,273 @@
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    fs,
+    path::Path,
+    sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Mutex, MutexGuard},
+    thread,
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Context, Result};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CpuProfileConfig {
+    pub enabled: bool,
+    // Frames to record before the profile is saved, counting from startup so loading is included
+    pub frames: u32,
+    // Chrome trace_event JSON, open it in chrome://tracing or ui.perfetto.dev
+    pub output: String,
+}
+
+impl Default for CpuProfileConfig {
+    fn default() -> CpuProfileConfig {
+        CpuProfileConfig {
+            enabled: false,
+            frames: 60,
+            output: "profile.json".to_string(),
+        }
+    }
+}
+
+// Spans from every thread end up in one session. Checking RECORDING first keeps spans down to an
+// atomic load while nothing is recorded
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+struct Session {
+    start: Instant,
+    events: Vec<TraceEvent>,
+    // Threads that recorded something, with their names
+    threads: Vec<(u64, String)>,
+}
+
+impl Session {
+    // Rounded to nanoseconds, since that's all Instant has
+    fn micros(&self, time: Instant) -> f64 {
+        time.duration_since(self.start).as_nanos() as f64 / 1000.0
+    }
+
+    fn register_thread(&mut self, tid: u64) {
+        if !self.threads.iter().any(|(id, _)| *id == tid) {
+            let name = thread::current().name().map(str::to_string).unwrap_or_else(|| format!("thread {}", tid));
+            self.threads.push((tid, name));
+        }
+    }
+}
+
+fn session() -> MutexGuard<'static, Option<Session>> {
+    // A panic while holding the lock only loses the event that was being pushed
+    SESSION.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn thread_id() -> u64 {
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+// One entry of the traceEvents array, see the Trace Event Format document for the fields.
+// Spans are complete events ("X"), markers are instant events ("i") and thread names are
+// metadata events ("M"). Times are in microseconds since the profile started
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    pub name: String,
+    pub cat: String,
+    pub ph: String,
+    pub ts: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dur: Option<f64>,
+    pub pid: u32,
+    pub tid: u64,
+    // Instant events are drawn across the whole process with "p"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub trace_events: Vec<TraceEvent>,
+    pub display_time_unit: String,
+}
+
+impl Profile {
+    pub fn spans(&self) -> impl Iterator<Item = &TraceEvent> {
+        self.trace_events.iter().filter(|event| event.ph == "X")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context(parent.display())?;
+        }
+        let json = serde_json::to_string(self).map_err(std::io::Error::from).context(path.display())?;
+        fs::write(path, json).context(path.display())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Profile> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).context(path.display())?;
+        serde_json::from_str(&json).map_err(std::io::Error::from).context(path.display())
+    }
+}
+
+// Start recording spans from every thread, throwing away anything recorded before
+pub fn start() {
+    *session() = Some(Session { start: Instant::now(), events: Vec::new(), threads: Vec::new() });
+    RECORDING.store(true, Ordering::Release);
+}
+
+// Stop recording and hand over everything recorded since start
+pub fn stop() -> Option<Profile> {
+    RECORDING.store(false, Ordering::Release);
+    let session = session().take()?;
+
+    let pid = std::process::id();
+    let mut trace_events: Vec<TraceEvent> = session.threads.iter()
+        .map(|(tid, name)| TraceEvent {
+            name: "thread_name".to_string(),
+            cat: "__metadata".to_string(),
+            ph: "M".to_string(),
+            ts: 0.0,
+            dur: None,
+            pid,
+            tid: *tid,
+            s: None,
+            args: Some(serde_json::json!({ "name": name })),
+        })
+        .collect();
+    trace_events.extend(session.events.into_iter().map(|event| TraceEvent { pid, ..event }));
+
+    Some(Profile { trace_events, display_time_unit: "ms".to_string() })
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+// Records the time until the returned span is dropped under this name
+#[must_use = "the span ends when it is dropped"]
+pub fn span(name: impl Into<Cow<'static, str>>) -> Span {
+    if is_recording() {
+        Span { name: Some(name.into()), start: Instant::now() }
+    } else {
+        Span { name: None, start: Instant::now() }
+    }
+}
+
+// A marker at this moment, like the end of a frame
+pub fn instant(name: impl Into<Cow<'static, str>>) {
+    if !is_recording() {
+        return;
+    }
+    let now = Instant::now();
+    let tid = thread_id();
+    if let Some(session) = session().as_mut() {
+        session.register_thread(tid);
+        let ts = session.micros(now);
+        session.events.push(TraceEvent {
+            name: name.into().into_owned(),
+            cat: "gloom".to_string(),
+            ph: "i".to_string(),
+            ts,
+            dur: None,
+            pid: 0,
+            tid,
+            s: Some("p".to_string()),
+            args: None,
+        });
+    }
+}
+
+pub struct Span {
+    // None when nothing was recording as the span started
+    name: Option<Cow<'static, str>>,
+    start: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let name = match self.name.take() {
+            Some(name) if is_recording() => name,
+            _ => return,
+        };
+        let end = Instant::now();
+        let tid = thread_id();
+        if let Some(session) = session().as_mut() {
+            session.register_thread(tid);
+            let start = self.start.max(session.start);
+            let ts = session.micros(start);
+            session.events.push(TraceEvent {
+                name: name.into_owned(),
+                cat: "gloom".to_string(),
+                ph: "X".to_string(),
+                ts,
+                dur: Some(end.duration_since(start).as_nanos() as f64 / 1000.0),
+                pid: 0,
+                tid,
+                s: None,
+                args: None,
+            });
+        }
+    }
+}
+
+// Records spans for the configured number of frames and saves them, for the runner
+pub struct CpuCapture {
+    config: CpuProfileConfig,
+    frames: u32,
+    saved: bool,
+}
+
+impl CpuCapture {
+    pub fn start(config: &CpuProfileConfig) -> CpuCapture {
+        start();
+        CpuCapture { config: config.clone(), frames: 0, saved: false }
+    }
+
+    // Returns where the profile was saved once enough frames are recorded
+    pub fn end_frame(&mut self) -> Result<Option<String>> {
+        if self.saved {
+            return Ok(None);
+        }
+        instant(format!("frame {}", self.frames));
+        self.frames += 1;
+        if self.frames < self.config.frames {
+            return Ok(None);
+        }
+        self.save()
+    }
+
+    // Save whatever was recorded if the app stops before enough frames are recorded
+    pub fn finish(mut self) -> Result<Option<String>> {
+        if self.saved {
+            return Ok(None);
+        }
+        self.save()
+    }
+
+    fn save(&mut self) -> Result<Option<String>> {
+        self.saved = true;
+        match stop() {
+            Some(profile) => {
+                profile.save(&self.config.output)?;
+                Ok(Some(self.config.output.clone()))
+            },
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{io::{self, Write}, path::PathBuf, thread};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, SyncSender};
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::error::{Context, Error, Result};
+use crate::screenshot;
+
+// Frames waiting to be written. Rendering blocks when the writer falls this far behind, which
+// keeps memory in check when encoding is slower than rendering
+const QUEUE_LENGTH: usize = 8;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+    // Simulated frames per second. Every frame advances time by exactly 1 / frame_rate, no
+    // matter how long it took to render
+    pub frame_rate: f32,
+    // Stop after this many frames, None records until the window is closed
+    pub frames: Option<u32>,
+    // Numbered PNGs are written to a new timestamped directory in here
+    pub directory: String,
+    // Pipe raw RGB frames to this command instead of writing PNGs. "{width}", "{height}" and
+    // "{frame_rate}" in the arguments are replaced
+    pub encoder: Option<Vec<String>>,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> RecordingConfig {
+        RecordingConfig {
+            enabled: false,
+            frame_rate: 60.0,
+            frames: None,
+            directory: "recordings".to_string(),
+            encoder: None,
+        }
+    }
+}
+
+// Writes every rendered frame to disk or to an encoder, on a separate thread
+pub struct Recorder {
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: f32,
+    frames_recorded: u32,
+    frame_limit: Option<u32>,
+    sender: Option<SyncSender<RgbaImage>>,
+    writer: Option<thread::JoinHandle<Result<()>>>,
+    // Where the frames end up, for messages
+    destination: String,
+}
+
+impl Recorder {
+    // Frames have to be exactly width by height, so the encoder gets a consistent stream
+    pub fn new(config: &RecordingConfig, width: u32, height: u32, frame_rate: f32) -> Result<Recorder> {
+        let (sender, receiver) = mpsc::sync_channel::<RgbaImage>(QUEUE_LENGTH);
+
+        let (writer, destination) = match &config.encoder {
+            Some(command) => {
+                let mut encoder = spawn_encoder(command, width, height, frame_rate)?;
+                let name = command[0].clone();
+                let destination = name.clone();
+                let writer = thread::spawn(move || {
+                    // Always wait for the encoder, even if writing failed, so it isn't left behind
+                    let written = match encoder.stdin.take() {
+                        Some(mut stdin) => receiver.iter().try_for_each(|frame| write_rgb(&mut stdin, &frame)),
+                        None => Ok(()),
+                    };
+                    let status = encoder.wait();
+                    written.context(&name)?;
+                    match status.context(&name)? {
+                        status if status.success() => Ok(()),
+                        status => Err(io::Error::other(format!("encoder exited with {}", status))).context(&name),
+                    }
+                });
+                (writer, destination)
+            },
+            None => {
+                let directory = PathBuf::from(&config.directory)
+                    .join(format!("recording-{}", screenshot::timestamp()));
+                let destination = directory.display().to_string();
+                let writer = thread::spawn(move || {
+                    for (index, frame) in receiver.iter().enumerate() {
+                        screenshot::save(&frame, directory.join(format!("frame-{:06}.png", index)))?;
+                    }
+                    Ok(())
+                });
+                (writer, destination)
+            },
+        };
+
+        println!("Recording {}x{} at {} fps to {}", width, height, frame_rate, destination);
+        Ok(Recorder {
+            width,
+            height,
+            frame_rate,
+            frames_recorded: 0,
+            frame_limit: config.frames,
+            sender: Some(sender),
+            writer: Some(writer),
+            destination,
+        })
+    }
+
+    // How far time moves every frame
+    pub fn time_step(&self) -> f32 {
+        1.0 / self.frame_rate
+    }
+
+    pub fn frames_recorded(&self) -> u32 {
+        self.frames_recorded
+    }
+
+    // True once the configured number of frames has been recorded
+    pub fn is_done(&self) -> bool {
+        self.frame_limit.is_some_and(|limit| self.frames_recorded >= limit)
+    }
+
+    pub fn record(&mut self, frame: RgbaImage) -> Result<()> {
+        if frame.width() != self.width || frame.height() != self.height {
+            return Err(Error::Texture(format!(
+                "recorded frames have to stay {}x{}, got {}x{}",
+                self.width, self.height, frame.width(), frame.height()
+            )));
+        }
+
+        let sent = self.sender.as_ref().map(|sender| sender.send(frame).is_ok()).unwrap_or(false);
+        if !sent {
+            // The writer stopped, finish tells why
+            return self.finish();
+        }
+        self.frames_recorded += 1;
+        Ok(())
+    }
+
+    // Stop recording and wait for every frame to be written
+    pub fn finish(&mut self) -> Result<()> {
+        drop(self.sender.take());
+        match self.writer.take() {
+            Some(writer) => {
+                let result = writer.join().unwrap_or_else(|_| {
+                    Err(Error::Io { path: self.destination.clone(), source: io::Error::other("recording writer panicked") })
+                });
+                if result.is_ok() {
+                    println!("Recorded {} frames to {}", self.frames_recorded, self.destination);
+                }
+                result
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("Failed to finish recording: {}", e);
+        }
+    }
+}
+
+fn spawn_encoder(command: &[String], width: u32, height: u32, frame_rate: f32) -> Result<Child> {
+    let (program, args) = command.split_first()
+        .ok_or_else(|| Error::Io { path: "encoder".to_string(), source: io::Error::other("encoder command is empty") })?;
+    let args = args.iter().map(|arg| {
+        arg.replace("{width}", &width.to_string())
+            .replace("{height}", &height.to_string())
+            .replace("{frame_rate}", &frame_rate.to_string())
+    });
+
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context(program)
+}
+
+fn write_rgb(out: &mut impl Write, frame: &RgbaImage) -> io::Result<()> {
+    let rgb: Vec<u8> = frame.pixels().flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+    out.write_all(&rgb)
+}
@@ -0,0 +1,732 @@
+use std::{error::Error, thread};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use glutin::event::{Event, StartCause, WindowEvent, KeyboardInput, ElementState::Pressed};
+use glutin::event_loop::{ControlFlow, EventLoop};
+
+use crate::app::{App, AppContext};
+use crate::config::{self, Command, Config};
+use crate::crash::{self, CrashKind, CrashReport};
+use crate::display::{SwapControl, WindowMode};
+use crate::error::{self, Context};
+use crate::gl_utils::{
+    backend,
+    bindable::Bindable,
+    capabilities::Capabilities,
+    debug::{self, DebugOutput},
+    framebuffer::Framebuffer,
+    profiler::GpuProfiler
+};
+use crate::headless::HeadlessContext;
+use crate::profiling::{self, CpuCapture};
+use crate::recording::Recorder;
+use crate::screenshot::{self, ScreenshotCapture};
+use crate::stats::{Benchmark, FrameStats};
+use crate::trace::FrameCapture;
+use crate::events::{RenderEvent, RunnerEvent};
+use crate::input::{
+    actions::Actions,
+    bindings::{Binding, Bindings},
+    event::InputEvent,
+    state::InputState
+};
+
+// How long to wait for the render thread to finish after the window is closed before giving up on it
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Parse the config from the command line and run the app, exiting on invalid arguments
+pub fn run_with_args<A, F>(create_app: F) -> !
+where
+    A: App,
+    F: FnOnce() -> A + Send + 'static
+{
+    // Load the config file with command line overrides
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(Command::Run(config)) => *config,
+        Ok(Command::Help) => {
+            println!("{}", config::USAGE);
+            std::process::exit(0);
+        },
+        Err(e) => {
+            eprintln!("{}", error::chain(&e));
+            std::process::exit(2);
+        }
+    };
+
+    if config.headless.enabled {
+        run_headless(config, create_app)
+    }
+    run(config, create_app)
+}
+
+// Render the configured number of frames without a window and exit, saving the last frame if
+// an output path is set
+pub fn run_headless<A, F>(config: Config, create_app: F) -> !
+where
+    A: App,
+    F: FnOnce() -> A
+{
+    let result = crash::catch(|| {
+        let frame = render_headless(&config, create_app)
+            .map_err(|e| Box::new(CrashReport::from_error(e.as_ref())))?;
+        if let Some(path) = &config.headless.output {
+            frame.save(path)
+                .context(path)
+                .map_err(|e| Box::new(CrashReport::from_error(&e)))?;
+            println!("Saved frame to {}", path);
+        }
+        Ok(())
+    }).and_then(|result| result);
+
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(crash) => {
+            report_crash(&crash, &config.diagnostics.crash_report);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Print the crash and save it if a report path is configured
+fn report_crash(crash: &CrashReport, path: &Option<String>) {
+    eprintln!("{}", crash);
+    if let Some(path) = path {
+        match crash.write_to(path) {
+            Ok(written) => eprintln!("Crash report written to {}", written),
+            Err(e) => eprintln!("Failed to write crash report to {}: {}", path, e),
+        }
+    }
+}
+
+// Render config.headless.frames frames into an offscreen framebuffer on this thread and return
+// the last one. The context only lives for the duration of the call
+pub fn render_headless<A, F>(config: &Config, create_app: F) -> Result<image::RgbaImage, Box<dyn Error>>
+where
+    A: App,
+    F: FnOnce() -> A
+{
+    let mut cpu_capture = start_cpu_profile(config);
+    let context = {
+        let _span = profiling::span("create context");
+        HeadlessContext::new(config.context.debug)?
+    };
+    let capabilities = Capabilities::query();
+    if config.diagnostics.gl_info {
+        println!("{}", capabilities);
+    }
+    debug::enable_labels(capabilities.supports_debug_output());
+
+    let (width, height) = (config.window.width, config.window.height);
+    let mut trace = start_trace(config, &capabilities);
+    let framebuffer = Framebuffer::new(width, height, u32::from(config.context.msaa))?;
+    framebuffer.bind();
+    setup_gl(config);
+    backend::current().viewport(0, 0, width as i32, height as i32);
+    let debug_output = install_debug_output(config, &capabilities);
+    let profiler = create_profiler(config, &capabilities);
+
+    let bindings = Bindings::default();
+    let input = InputState::new();
+    let mut recorder = if config.recording.enabled {
+        Some(Recorder::new(&config.recording, width, height, config.recording.frame_rate)?)
+    } else {
+        None
+    };
+    // A recording steps at its own frame rate, so it plays back at the right speed
+    let time_step = recorder.as_ref().map_or(1.0 / config.headless.frame_rate, Recorder::time_step);
+    let app_context = |frame: u32| AppContext {
+        runner: None,
+        config,
+        capabilities: &capabilities,
+        profiler: &profiler,
+        window_size: (width, height),
+        scale_factor: 1.0,
+        elapsed: config.headless.start_time + frame as f32 * time_step,
+    };
+
+    // A benchmark renders as many frames as it needs instead of headless.frames
+    let mut stats = FrameStats::new(&config.stats)?;
+    let frames = stats.benchmark().map_or(config.headless.frames, Benchmark::total_frames);
+
+    let mut app = create_app();
+    {
+        let _span = profiling::span("app init");
+        app.init(&app_context(0))?;
+    }
+    for frame in 0..frames {
+        let frame_span = profiling::span("frame");
+        let frame_start = Instant::now();
+        let ctx = app_context(frame);
+        render_frame(&mut app, &ctx, time_step, &Actions::new(&input, &bindings), config);
+        // Nothing waits for the GPU without a window, so the frame time would only cover
+        // submitting the calls otherwise
+        if stats.is_measuring() {
+            let _span = profiling::span("finish");
+            backend::current().finish();
+        }
+        stats.end_frame(frame_start.elapsed().as_secs_f32(), ctx.elapsed);
+        if let Some(debug_output) = &debug_output {
+            debug_output.check();
+        }
+        if let Some(capture) = &mut trace {
+            end_trace_frame(capture, width, height);
+        }
+        if let Some(recorder) = &mut recorder {
+            let _span = profiling::span("record frame");
+            recorder.record(framebuffer.read_pixels()?)?;
+            if recorder.is_done() {
+                break;
+            }
+        }
+        // The frame has to end before the profile can be saved with it
+        drop(frame_span);
+        if let Some(capture) = &mut cpu_capture {
+            report_cpu_profile(capture.end_frame());
+        }
+    }
+    let image = framebuffer.read_pixels()?;
+    if let Some(mut recorder) = recorder {
+        recorder.finish()?;
+    }
+
+    stats.finish();
+    // Headless runs are short, so only the last frame's GPU times are printed
+    if config.profiler.enabled && config.profiler.print_interval > 0.0 {
+        profiler.finish();
+        if let Some(gpu_frame) = profiler.latest() {
+            println!("{}", gpu_frame);
+        }
+    }
+
+    app.shutdown(&app_context(frames));
+    drop(app);
+    if let Some(capture) = cpu_capture {
+        report_cpu_profile(capture.finish());
+    }
+    drop(profiler);
+    drop(trace);
+    drop(debug_output);
+    drop(framebuffer);
+    drop(context);
+    Ok(image)
+}
+
+// GL state shared by the windowed and headless renderers
+fn setup_gl(config: &Config) {
+    let gl = backend::current();
+    gl.enable(gl::CULL_FACE);
+    if config.context.msaa > 0 {
+        gl.enable(gl::MULTISAMPLE);
+    } else {
+        gl.disable(gl::MULTISAMPLE);
+    }
+    gl.enable(gl::BLEND);
+    gl.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+}
+
+// Has to start before setup_gl and the offscreen framebuffer is created, so the trace has all
+// the state the frame depends on
+fn start_trace(config: &Config, capabilities: &Capabilities) -> Option<FrameCapture> {
+    if config.trace.enabled {
+        Some(FrameCapture::install(&config.trace, capabilities))
+    } else {
+        None
+    }
+}
+
+fn end_trace_frame(capture: &mut FrameCapture, width: u32, height: u32) {
+    match capture.end_frame(width, height) {
+        Ok(Some(path)) => println!("Saved GL trace to {}", path),
+        Ok(None) => (),
+        Err(e) => eprintln!("Failed to save GL trace: {}", e),
+    }
+}
+
+// Started as early as possible, so loading shows up in the profile
+fn start_cpu_profile(config: &Config) -> Option<CpuCapture> {
+    if config.cpu_profile.enabled {
+        Some(CpuCapture::start(&config.cpu_profile))
+    } else {
+        None
+    }
+}
+
+fn report_cpu_profile(saved: crate::error::Result<Option<String>>) {
+    match saved {
+        Ok(Some(path)) => println!("Saved CPU profile to {}", path),
+        Ok(None) => (),
+        Err(e) => eprintln!("Failed to save CPU profile: {}", e),
+    }
+}
+
+fn create_profiler(config: &Config, capabilities: &Capabilities) -> GpuProfiler {
+    if !config.profiler.enabled {
+        GpuProfiler::disabled()
+    } else if !capabilities.supports_timer_queries() {
+        eprintln!("Timer queries are not supported by this driver, GPU times won't be measured");
+        GpuProfiler::disabled()
+    } else {
+        GpuProfiler::new(capabilities)
+    }
+}
+
+fn install_debug_output(config: &Config, capabilities: &Capabilities) -> Option<DebugOutput> {
+    if !config.context.debug {
+        None
+    } else if !capabilities.supports_debug_output() {
+        eprintln!("Debug output is not supported by this driver, OpenGL errors won't be reported");
+        None
+    } else {
+        Some(DebugOutput::install(config.debug_output.clone()))
+    }
+}
+
+// Update and draw one frame of the app into whatever framebuffer is bound. Clearing and the
+// app's render are separate passes, for the GPU profiler and as debug groups
+fn render_frame<A: App>(app: &mut A, ctx: &AppContext, delta_time: f32, actions: &Actions, config: &Config) {
+    {
+        let _span = profiling::span("update");
+        app.update(ctx, delta_time, actions);
+    }
+
+    ctx.profiler.begin_frame();
+    {
+        let _span = profiling::span("clear");
+        let _clear = ctx.profiler.scope("clear");
+        let _group = debug::group("clear");
+        let gl = backend::current();
+        let [r, g, b, a] = config.renderer.clear_color;
+        gl.clear_color(r, g, b, a);
+        gl.clear(gl::COLOR_BUFFER_BIT);
+    }
+    {
+        let _span = profiling::span("render");
+        let _render = ctx.profiler.scope("render");
+        let _group = debug::group("render");
+        app.render(ctx);
+    }
+    ctx.profiler.end_frame();
+}
+
+// Open a window and run the app until the window is closed. The app is created on the render
+// thread, so it is free to hold on to things that can't leave it, like GL objects
+pub fn run<A, F>(config: Config, create_app: F) -> !
+where
+    A: App,
+    F: FnOnce() -> A + Send + 'static
+{
+    let mut display_settings = config.display_settings();
+    let mut cpu_capture = start_cpu_profile(&config);
+    let create_window = profiling::span("create window");
+
+    // Set up the necessary objects to deal with windows and event handling
+    let el = EventLoop::<RunnerEvent>::with_user_event();
+    let wb = glutin::window::WindowBuilder::new()
+        .with_title(&config.window.title)
+        // Recorded frames all have to be the size of the first one
+        .with_resizable(!config.recording.enabled)
+        .with_inner_size(glutin::dpi::LogicalSize::new(config.window.width, config.window.height));
+    let cb = glutin::ContextBuilder::new()
+        .with_vsync(display_settings.vsync)
+        .with_multisampling(config.context.msaa)
+        .with_gl_debug_flag(config.context.debug);
+    // Fails for context settings the driver can't provide, like too many MSAA samples
+    let windowed_context = match cb.build_windowed(wb, &el) {
+        Ok(windowed_context) => windowed_context,
+        Err(e) => {
+            let e = error::Error::Context(format!("failed to create a window with the requested context: {}", e));
+            report_crash(&CrashReport::from_error(&e), &config.diagnostics.crash_report);
+            std::process::exit(1);
+        }
+    };
+    // The window stays with the event loop, since winit only allows changing it from this thread.
+    // Safety: the window outlives the context, which the render thread drops before it exits,
+    // and the event loop only exits once the render thread has stopped or stopped responding
+    let (raw_context, window) = unsafe { windowed_context.split() };
+    if display_settings.mode != WindowMode::Windowed {
+        if let Err(e) = display_settings.apply_window_mode(&window) {
+            eprintln!("Failed to change window mode: {}", e);
+        }
+    }
+    let initial_size = window.inner_size();
+    let mut scale_factor = window.scale_factor();
+    drop(create_window);
+
+    // Load key bindings, falling back to the compiled in defaults if the file is broken
+    let bindings = match Bindings::load(&config.input.bindings) {
+        Ok(bindings) => bindings,
+        Err(e) => {
+            eprintln!("{}, using default bindings", error::chain(&e));
+            Bindings::default()
+        }
+    };
+    let render_bindings = bindings.clone();
+
+    // Set up a channel for sending input and window events to the render thread, which builds its own
+    // InputState from them every frame
+    let (render_sender, render_receiver) = mpsc::channel::<RenderEvent>();
+
+    let crash_report_path = config.diagnostics.crash_report.clone();
+    // The event loop owns the window mode, the render thread only keeps track of vsync
+    let mut window_settings = display_settings.clone();
+    let runner_proxy = el.create_proxy();
+
+    // Spawn a separate thread for rendering, so event handling doesn't block rendering.
+    // Panics and errors are caught and turned into a report for the event loop
+    let render_thread = thread::Builder::new().name("render".to_string()).spawn(move || crash::catch(move || {
+        // Acquire the OpenGL Context and load the function pointers. This has to be done inside of the renderin thread, because
+        // an active OpenGL context cannot safely traverse a thread boundary
+        let context = unsafe {
+            let c = raw_context.make_current()
+                .map_err(|(_, e)| Box::new(CrashReport::from_error(&error::Error::Context(e.to_string()))))?;
+            gl::load_with(|symbol| c.get_proc_address(symbol) as *const _);
+            c
+        };
+
+        let capabilities = Capabilities::query();
+        if config.diagnostics.gl_info {
+            println!("{}", capabilities);
+        }
+        debug::enable_labels(capabilities.supports_debug_output());
+        if i32::from(config.context.msaa) > capabilities.limits.max_samples {
+            eprintln!("The driver supports at most {} MSAA samples, {} were requested",
+                capabilities.limits.max_samples, config.context.msaa);
+        }
+
+        // Vsync can only be chosen when creating the context, so changing it later is done through
+        // the platform's swap interval function instead
+        let swap_control = SwapControl::load(&context);
+        if !swap_control.is_supported() {
+            eprintln!("Vsync can't be toggled at runtime with this driver");
+        }
+
+        let mut window_size = (initial_size.width, initial_size.height);
+        let mut scale_factor = scale_factor;
+
+        // Set up openGL
+        let mut trace = start_trace(&config, &capabilities);
+        setup_gl(&config);
+        backend::current().viewport(0, 0, window_size.0 as i32, window_size.1 as i32);
+        let debug_output = install_debug_output(&config, &capabilities);
+        let profiler = create_profiler(&config, &capabilities);
+        let mut last_profile_print = Instant::now();
+        let mut screenshots = ScreenshotCapture::new(&config.screenshots.directory, config.screenshots.keep_alpha);
+        let check_debug_output = || {
+            if let Some(debug_output) = &debug_output {
+                debug_output.check();
+            }
+        };
+
+        // While recording, time advances by a fixed step every frame instead of following the clock
+        let mut recorder = if config.recording.enabled {
+            let recorder = Recorder::new(&config.recording, window_size.0, window_size.1, config.recording.frame_rate)
+                .map_err(|e| Box::new(CrashReport::from_error(&e)))?;
+            Some(recorder)
+        } else {
+            None
+        };
+
+        let mut stats = FrameStats::new(&config.stats)
+            .map_err(|e| Box::new(CrashReport::from_error(&e)))?;
+        if stats.benchmark().is_some() && display_settings.vsync {
+            eprintln!("Vsync is on, so the benchmark can't go faster than the refresh rate");
+        }
+
+        let first_frame_time = Instant::now();
+        let app_context = |window_size: (u32, u32), scale_factor: f64, elapsed: f32| AppContext {
+            runner: Some(&runner_proxy),
+            config: &config,
+            capabilities: &capabilities,
+            profiler: &profiler,
+            window_size,
+            scale_factor,
+            elapsed,
+        };
+
+        let mut elapsed = 0.0;
+        let mut app = create_app();
+        let init_span = profiling::span("app init");
+        if let Err(e) = app.init(&app_context(window_size, scale_factor, elapsed)) {
+            eprintln!("Failed to initialize app");
+            return Err(Box::new(CrashReport::from_error(e.as_ref())));
+        }
+        drop(init_span);
+        check_debug_output();
+
+        let mut last_frame_time = first_frame_time;
+        let mut last_frame_end = Instant::now();
+        let mut input = InputState::new();
+        let mut running = true;
+        // The main rendering loop
+        while running {
+            let frame_span = profiling::span("frame");
+            // Handle events from the event loop. Resizes are coalesced so we only resize once per frame
+            let events_span = profiling::span("handle events");
+            input.begin_frame();
+            let mut resized = false;
+            let mut events: Vec<RenderEvent> = render_receiver.try_iter().collect();
+            loop {
+                for event in events.drain(..) {
+                    match event {
+                        RenderEvent::Input(input_event) => input.process(input_event),
+                        RenderEvent::Resized { width, height, scale_factor: new_scale_factor } => {
+                            window_size = (width, height);
+                            scale_factor = new_scale_factor;
+                            resized = true;
+                        },
+                        RenderEvent::Shutdown => {
+                            running = false;
+                            continue;
+                        },
+                    }
+                    app.on_event(&app_context(window_size, scale_factor, elapsed), &event);
+                }
+
+                if !running || (window_size.0 != 0 && window_size.1 != 0) {
+                    break;
+                }
+
+                // Pause rendering while minimized, there is nothing to draw to
+                let _span = profiling::span("wait while minimized");
+                match render_receiver.recv() {
+                    Ok(event) => events.push(event),
+                    // The event loop is gone, so nobody will tell us to shut down
+                    Err(_) => events.push(RenderEvent::Shutdown),
+                }
+                // Don't let the app jump from the time spent minimized
+                last_frame_time = Instant::now();
+                last_frame_end = last_frame_time;
+            }
+
+            drop(events_span);
+            if !running {
+                break;
+            }
+
+            if resized {
+                let _span = profiling::span("resize");
+                let (width, height) = window_size;
+                context.resize(glutin::dpi::PhysicalSize::new(width, height));
+                backend::current().viewport(0, 0, width as i32, height as i32);
+            }
+
+            let now = Instant::now();
+            let delta_time = match &recorder {
+                Some(recorder) => recorder.time_step(),
+                None => now.duration_since(last_frame_time).as_secs_f32(),
+            };
+            elapsed = match &recorder {
+                Some(recorder) => recorder.frames_recorded() as f32 * recorder.time_step(),
+                None => now.duration_since(first_frame_time).as_secs_f32(),
+            };
+            last_frame_time = now;
+            let ctx = app_context(window_size, scale_factor, elapsed);
+
+            let actions = Actions::new(&input, &render_bindings);
+            if actions.is_pressed("cycle_window_mode") && recorder.is_some() {
+                eprintln!("The window mode can't change while recording");
+            } else if actions.is_pressed("cycle_window_mode") {
+                // The event loop is gone if this fails, and the render thread is about to be told to stop
+                let _ = runner_proxy.send_event(RunnerEvent::CycleWindowMode);
+            }
+            if actions.is_pressed("toggle_vsync") {
+                match swap_control.set_vsync(!display_settings.vsync) {
+                    Ok(()) => display_settings.vsync = !display_settings.vsync,
+                    Err(e) => eprintln!("Failed to toggle vsync: {}", e),
+                }
+            }
+
+            render_frame(&mut app, &ctx, delta_time, &actions, &config);
+            if let Some(capture) = &mut trace {
+                end_trace_frame(capture, window_size.0, window_size.1);
+            }
+
+            // Read back the frame before it gets swapped away. Recording reads synchronously, since
+            // the simulated time doesn't care how long the frame took
+            if let Some(active) = &mut recorder {
+                let _span = profiling::span("record frame");
+                let recorded = screenshot::read_pixels(window_size.0, window_size.1, false)
+                    .and_then(|frame| active.record(frame));
+                if let Err(e) = recorded {
+                    eprintln!("Recording stopped: {}", e);
+                    recorder = None;
+                } else if active.is_done() {
+                    // Closing the window when done makes recording scriptable
+                    running = false;
+                }
+            }
+            if actions.is_pressed("screenshot") {
+                let _span = profiling::span("screenshot");
+                if let Err(e) = screenshots.capture(window_size.0, window_size.1) {
+                    eprintln!("Failed to take screenshot: {}", e);
+                }
+            }
+
+            {
+                let _span = profiling::span("swap_buffers");
+                context.swap_buffers().unwrap();
+            }
+            screenshots.poll();
+            check_debug_output();
+
+            let frame_end = Instant::now();
+            stats.end_frame(frame_end.duration_since(last_frame_end).as_secs_f32(), elapsed);
+            last_frame_end = frame_end;
+            if let Some(mut title) = stats.title(&config.window.title) {
+                if let Some(gpu_frame) = profiler.latest() {
+                    title.push_str(&format!(" - GPU {:.2} ms", gpu_frame.total.as_secs_f64() * 1000.0));
+                }
+                let _ = runner_proxy.send_event(RunnerEvent::SetTitle(title));
+            }
+            let print_interval = config.profiler.print_interval;
+            if profiler.is_enabled() && print_interval > 0.0 && last_profile_print.elapsed().as_secs_f32() >= print_interval {
+                if let Some(gpu_frame) = profiler.latest() {
+                    println!("{}", gpu_frame);
+                }
+                last_profile_print = Instant::now();
+            }
+            if stats.is_benchmark_done() {
+                running = false;
+            }
+            drop(frame_span);
+            if let Some(capture) = &mut cpu_capture {
+                report_cpu_profile(capture.end_frame());
+            }
+        }
+        stats.finish();
+        if let Some(capture) = cpu_capture {
+            report_cpu_profile(capture.finish());
+        }
+
+        // Give the app a chance to save its state, then release its GL resources while the
+        // context is still current. The context itself is dropped last
+        app.shutdown(&app_context(window_size, scale_factor, elapsed));
+        drop(app);
+        drop(profiler);
+        drop(trace);
+        drop(recorder);
+        // Waits for screenshots that are still being saved
+        drop(screenshots);
+        drop(debug_output);
+        drop(context);
+        Ok(())
+    }).and_then(|result| result)).expect("Failed to spawn render thread");
+
+    // Keep track of the health of the rendering thread, and wake up the event loop when it stops.
+    // Sending an event means the event loop notices right away, even while waiting for input
+    let watchdog_proxy = el.create_proxy();
+    thread::spawn(move || {
+        let crash = match render_thread.join() {
+            Ok(result) => result.err(),
+            // Only happens if the panic escaped crash::catch, so there's no backtrace
+            Err(_) => Some(Box::new(CrashReport {
+                kind: CrashKind::Panic,
+                thread: "render".to_string(),
+                message: "panicked outside of the render loop".to_string(),
+                causes: Vec::new(),
+                location: None,
+                backtrace: None,
+            })),
+        };
+        // Fails if the event loop is already gone, in which case there's nobody left to tell
+        let _ = watchdog_proxy.send_event(RunnerEvent::RenderThreadStopped { crash });
+    });
+
+    // Set once the render thread has been asked to shut down
+    let mut shutdown_deadline: Option<Instant> = None;
+    let mut exit_code = 0;
+
+    // Start the event loop -- This is where window events get handled
+    el.run(move |event, _, control_flow| {
+        *control_flow = match shutdown_deadline {
+            Some(deadline) => ControlFlow::WaitUntil(deadline),
+            None => ControlFlow::Wait,
+        };
+
+        let _span = match &event {
+            Event::WindowEvent { .. } => Some(profiling::span("window event")),
+            Event::DeviceEvent { .. } => Some(profiling::span("device event")),
+            Event::UserEvent(_) => Some(profiling::span("runner event")),
+            _ => None,
+        };
+
+        // Forward input and window changes to the rendering thread
+        let render_event = match &event {
+            Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {
+                Some(RenderEvent::Resized { width: size.width, height: size.height, scale_factor })
+            },
+            Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { scale_factor: new_scale_factor, new_inner_size }, .. } => {
+                scale_factor = *new_scale_factor;
+                Some(RenderEvent::Resized { width: new_inner_size.width, height: new_inner_size.height, scale_factor })
+            },
+            Event::WindowEvent { event, .. } => InputEvent::from_window_event(event).map(RenderEvent::Input),
+            Event::DeviceEvent { event, .. } => InputEvent::from_device_event(event).map(RenderEvent::Input),
+            _ => None,
+        };
+        if let Some(render_event) = render_event {
+            // The render thread has stopped if this fails, which the watchdog takes care of
+            let _ = render_sender.send(render_event);
+        }
+
+        let mut quit = false;
+        match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                quit = true;
+            },
+            // Handle quitting here instead of in the render thread, so it works even if rendering hangs
+            Event::WindowEvent { event: WindowEvent::KeyboardInput {
+                input: KeyboardInput { state: Pressed, virtual_keycode: Some(keycode), .. }, .. }, .. }
+                if bindings.is_bound("quit", Binding::Key(keycode)) => {
+                quit = true;
+            },
+            Event::WindowEvent { event: WindowEvent::MouseInput { state: Pressed, button, .. }, .. }
+                if bindings.is_bound("quit", Binding::Mouse(button)) => {
+                quit = true;
+            },
+            // Only exit once the render thread is done, so it isn't killed in the middle of a frame
+            Event::UserEvent(RunnerEvent::RenderThreadStopped { crash }) => {
+                if let Some(crash) = crash {
+                    report_crash(&crash, &crash_report_path);
+                    exit_code = 1;
+                }
+                *control_flow = ControlFlow::Exit;
+            },
+            Event::UserEvent(RunnerEvent::CycleWindowMode) => {
+                let previous_mode = window_settings.mode;
+                window_settings.mode = window_settings.mode.next();
+                if let Err(e) = window_settings.apply_window_mode(&window) {
+                    eprintln!("Failed to change window mode: {}", e);
+                    window_settings.mode = previous_mode;
+                }
+            },
+            Event::UserEvent(RunnerEvent::SetCursorGrab(grab)) => {
+                if let Err(e) = window.set_cursor_grab(grab) {
+                    eprintln!("Failed to grab cursor: {}", e);
+                }
+                window.set_cursor_visible(!grab);
+            },
+            Event::UserEvent(RunnerEvent::SetTitle(title)) => {
+                window.set_title(&title);
+            },
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                eprintln!("Render thread did not shut down within {:?}, exiting anyway", SHUTDOWN_TIMEOUT);
+                exit_code = 1;
+                *control_flow = ControlFlow::Exit;
+            },
+            // winit exits with 0 after this, so a failure has to exit on its own
+            Event::LoopDestroyed if exit_code != 0 => {
+                std::process::exit(exit_code);
+            },
+            _ => { }
+        }
+
+        if quit && shutdown_deadline.is_none() {
+            // The render thread has stopped if this fails, which the watchdog takes care of
+            let _ = render_sender.send(RenderEvent::Shutdown);
+            let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+            shutdown_deadline = Some(deadline);
+            *control_flow = ControlFlow::WaitUntil(deadline);
+        }
+    });
+}
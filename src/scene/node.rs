@@ -0,0 +1,122 @@
+use std::rc::Rc;
+
+use crate::error::Result;
+use crate::gl_utils::{
+    triangle::Triangle,
+    bindable::Bindable,
+    debug,
+    shaders::program::Program
+};
+
+// A node in the scene graph. Each node has a transform relative to its parent, and optionally
+// something to draw. Moving a node moves all of its children along with it, so a rotor only has
+// to care about spinning around its own reference point and not where the helicopter is.
+pub struct SceneNode {
+    pub name: String,
+
+    pub position: glm::Vec3,
+    // Euler angles in radians, applied in the order yaw (y), pitch (x), roll (z)
+    pub rotation: glm::Vec3,
+    pub scale: glm::Vec3,
+    // Point (in local space) that the node rotates and scales around
+    pub reference_point: glm::Vec3,
+
+    pub mesh: Option<Rc<Triangle>>,
+    // Program is expected to have located the "c_trans" uniform before drawing
+    pub program: Option<Rc<Program>>,
+
+    pub children: Vec<SceneNode>,
+
+    world_transform: glm::Mat4,
+}
+
+impl SceneNode {
+    pub fn new(name: &str) -> SceneNode {
+        SceneNode {
+            name: name.to_string(),
+            position: glm::zero(),
+            rotation: glm::zero(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+            reference_point: glm::zero(),
+            mesh: None,
+            program: None,
+            children: vec![],
+            world_transform: glm::identity(),
+        }
+    }
+
+    // The mesh is labeled after the node, for debuggers
+    pub fn with_mesh(name: &str, mesh: Rc<Triangle>, program: Rc<Program>) -> SceneNode {
+        mesh.set_label(name);
+        let mut node = SceneNode::new(name);
+        node.mesh = Some(mesh);
+        node.program = Some(program);
+        node
+    }
+
+    // Returns the index of the new child, which can be used with child_mut later
+    pub fn add_child(&mut self, child: SceneNode) -> usize {
+        self.children.push(child);
+        self.children.len() - 1
+    }
+
+    pub fn child_mut(&mut self, index: usize) -> Option<&mut SceneNode> {
+        self.children.get_mut(index)
+    }
+
+    // Depth first search for a node with the given name, including self
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        if self.name == name {
+            return Some(self);
+        }
+
+        self.children.iter_mut().find_map(|child| child.find_mut(name))
+    }
+
+    // Transform from this node's space to its parent's space
+    pub fn local_transform(&self) -> glm::Mat4 {
+        let mut transform = glm::translation(&self.position);
+        transform = glm::translate(&transform, &self.reference_point);
+        transform = glm::rotate_y(&transform, self.rotation.y);
+        transform = glm::rotate_x(&transform, self.rotation.x);
+        transform = glm::rotate_z(&transform, self.rotation.z);
+        transform = glm::scale(&transform, &self.scale);
+        glm::translate(&transform, &(-self.reference_point))
+    }
+
+    // Transform from this node's space to world space, as of the last update_world_transforms
+    pub fn world_transform(&self) -> &glm::Mat4 {
+        &self.world_transform
+    }
+
+    // Recompute world transforms for this node and all of its descendants.
+    // Call this on the root with the identity matrix once per frame after animating.
+    pub fn update_world_transforms(&mut self, parent_transform: &glm::Mat4) {
+        self.world_transform = parent_transform * self.local_transform();
+
+        let world_transform = self.world_transform;
+        for child in self.children.iter_mut() {
+            child.update_world_transforms(&world_transform);
+        }
+    }
+
+    // Draw this node and all of its descendants. The shader receives view * world as "c_trans".
+    // Every node is drawn in a debug group named after it, so the groups follow the scene graph
+    pub fn draw(&self, view: &glm::Mat4) -> Result<()> {
+        let _group = debug::group(&self.name);
+        if let (Some(mesh), Some(program)) = (&self.mesh, &self.program) {
+            let c_trans = view * self.world_transform;
+            program.set_uniform("c_trans", c_trans)?;
+
+            program.bind();
+            mesh.draw();
+            program.unbind();
+        }
+
+        for child in self.children.iter() {
+            child.draw(view)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::{path::{Path, PathBuf}, rc::Rc, thread};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gl::types::GLuint;
+use image::RgbaImage;
+
+use crate::error::{Context, Result};
+use crate::gl_utils::backend::{self, Fence, GlBackend};
+
+// Read the color buffer of the bound read framebuffer, top row first. Blocks until the GPU has
+// finished drawing, use ScreenshotCapture to avoid the stall.
+//
+// The bytes are taken as they are stored, which is what ends up on screen. sRGB framebuffers
+// already store encoded values, so both kinds give a correct sRGB PNG without conversion
+pub fn read_pixels(width: u32, height: u32, keep_alpha: bool) -> Result<RgbaImage> {
+    read_pixels_with(backend::current().as_ref(), width, height, keep_alpha)
+}
+
+pub fn read_pixels_with(backend: &dyn GlBackend, width: u32, height: u32, keep_alpha: bool) -> Result<RgbaImage> {
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    backend.pixel_store(gl::PACK_ALIGNMENT, 1);
+    backend.read_pixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, &mut pixels);
+    backend.check_error("glReadPixels")?;
+    Ok(to_image(width, height, pixels, keep_alpha))
+}
+
+// Save an image, the format is decided by the file extension
+pub fn save<P: AsRef<Path>>(image: &RgbaImage, path: P) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent).context(parent.display())?;
+        }
+    }
+    image.save(path).context(path.display())
+}
+
+// File name for a screenshot taken now, like screenshot-2020-09-14-18-05-32-123.png
+pub fn timestamped_name(prefix: &str, extension: &str) -> String {
+    format!("{}-{}.{}", prefix, timestamp(), extension)
+}
+
+// The current UTC time down to milliseconds, in a form that sorts and is safe in file names
+pub fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = now.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let time_of_day = seconds % 86400;
+    format!("{:04}-{:02}-{:02}-{:02}-{:02}-{:02}-{:03}",
+        year, month, day,
+        time_of_day / 3600, time_of_day / 60 % 60, time_of_day % 60, now.subsec_millis())
+}
+
+// Days since 1970-01-01 to a UTC date, from http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn to_image(width: u32, height: u32, mut pixels: Vec<u8>, keep_alpha: bool) -> RgbaImage {
+    // Blending leaves the alpha of the default framebuffer all over the place, even though
+    // the window is shown opaque
+    if !keep_alpha {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+    }
+
+    // OpenGL starts at the bottom row
+    let image = RgbaImage::from_raw(width, height, pixels).expect("pixel buffer matches the image size");
+    image::imageops::flip_vertical(&image)
+}
+
+// A readback in flight. The pixels are copied into the buffer by the GPU, and are ready once
+// the fence has been passed
+struct Pending {
+    buffer: GLuint,
+    fence: Fence,
+    width: u32,
+    height: u32,
+    path: PathBuf,
+}
+
+// Takes screenshots without stalling the frame. The pixels are copied into a pixel buffer object
+// on the GPU, read back a frame or two later once the copy is done, and encoded to PNG on a
+// separate thread. Has to be used and dropped on the thread with the context
+pub struct ScreenshotCapture {
+    directory: PathBuf,
+    keep_alpha: bool,
+    pending: Vec<Pending>,
+    // Buffers that have been read back and can be reused
+    free_buffers: Vec<GLuint>,
+    encoders: Vec<thread::JoinHandle<()>>,
+    backend: Rc<dyn GlBackend>,
+}
+
+impl ScreenshotCapture {
+    pub fn new<P: Into<PathBuf>>(directory: P, keep_alpha: bool) -> ScreenshotCapture {
+        ScreenshotCapture {
+            directory: directory.into(),
+            keep_alpha,
+            pending: Vec::new(),
+            free_buffers: Vec::new(),
+            encoders: Vec::new(),
+            backend: backend::current(),
+        }
+    }
+
+    // Start reading back the bound read framebuffer into a timestamped PNG in the screenshot
+    // directory. Call after drawing and before swapping buffers. Returns the path it will be saved to
+    pub fn capture(&mut self, width: u32, height: u32) -> Result<PathBuf> {
+        let path = self.directory.join(timestamped_name("screenshot", "png"));
+        self.capture_to(width, height, path.clone())?;
+        Ok(path)
+    }
+
+    pub fn capture_to(&mut self, width: u32, height: u32, path: PathBuf) -> Result<()> {
+        let size = width as usize * height as usize * 4;
+        let backend = &self.backend;
+        let buffer = match self.free_buffers.pop() {
+            Some(buffer) => buffer,
+            None => {
+                let mut buffer = 0;
+                backend.gen_buffers(std::slice::from_mut(&mut buffer));
+                buffer
+            },
+        };
+
+        backend.bind_buffer(gl::PIXEL_PACK_BUFFER, buffer);
+        backend.allocate_buffer(gl::PIXEL_PACK_BUFFER, size, gl::STREAM_READ);
+        backend.pixel_store(gl::PACK_ALIGNMENT, 1);
+        // Reading into the pack buffer returns right away
+        backend.read_pixels_to_buffer(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, 0);
+        backend.bind_buffer(gl::PIXEL_PACK_BUFFER, 0);
+        let fence = backend.fence_sync();
+
+        if let Err(e) = backend.check_error("ScreenshotCapture::capture") {
+            backend.delete_sync(fence);
+            self.free_buffers.push(buffer);
+            return Err(e.into());
+        }
+
+        self.pending.push(Pending { buffer, fence, width, height, path });
+        Ok(())
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Hand finished readbacks to the encoder. Call once per frame, never blocks
+    pub fn poll(&mut self) {
+        self.collect(0);
+    }
+
+    // Wait for every screenshot to be read back and written to disk
+    pub fn finish(&mut self) {
+        // One second per readback is far more than the GPU should ever need
+        self.collect(1_000_000_000);
+        for encoder in self.encoders.drain(..) {
+            let _ = encoder.join();
+        }
+    }
+
+    fn collect(&mut self, timeout_ns: u64) {
+        self.encoders.retain(|encoder| !encoder.is_finished());
+
+        let mut still_pending = Vec::new();
+        for pending in self.pending.drain(..) {
+            let status = self.backend.client_wait_sync(pending.fence, gl::SYNC_FLUSH_COMMANDS_BIT, timeout_ns);
+            if status == gl::TIMEOUT_EXPIRED {
+                still_pending.push(pending);
+                continue;
+            }
+
+            self.backend.delete_sync(pending.fence);
+            if status == gl::WAIT_FAILED {
+                eprintln!("Failed to wait for screenshot {}", pending.path.display());
+                self.free_buffers.push(pending.buffer);
+                continue;
+            }
+
+            let mut pixels = vec![0u8; pending.width as usize * pending.height as usize * 4];
+            self.backend.bind_buffer(gl::PIXEL_PACK_BUFFER, pending.buffer);
+            self.backend.get_buffer_sub_data(gl::PIXEL_PACK_BUFFER, 0, &mut pixels);
+            self.backend.bind_buffer(gl::PIXEL_PACK_BUFFER, 0);
+            self.free_buffers.push(pending.buffer);
+            if let Err(e) = self.backend.check_error("ScreenshotCapture::poll") {
+                eprintln!("Failed to read back screenshot {}: {}", pending.path.display(), e);
+                continue;
+            }
+
+            // Encoding a PNG takes longer than a frame, so keep it off the render thread
+            let (width, height, path, keep_alpha) = (pending.width, pending.height, pending.path, self.keep_alpha);
+            self.encoders.push(thread::spawn(move || {
+                match save(&to_image(width, height, pixels, keep_alpha), &path) {
+                    Ok(()) => println!("Saved screenshot to {}", path.display()),
+                    Err(e) => eprintln!("Failed to save screenshot: {}", e),
+                }
+            }));
+        }
+        self.pending = still_pending;
+    }
+}
+
+impl Drop for ScreenshotCapture {
+    fn drop(&mut self) {
+        self.finish();
+        // Anything still pending after finish is given up on
+        for pending in self.pending.drain(..) {
+            self.backend.delete_sync(pending.fence);
+            self.backend.delete_buffers(&[pending.buffer]);
+        }
+        self.backend.delete_buffers(&self.free_buffers);
+    }
+}
@@ -0,0 +1,296 @@
+use std::{collections::VecDeque, fmt, fs::{self, File}, path::Path};
+use std::io::{BufWriter, Write};
+
+use serde::Deserialize;
+
+use crate::error::{Context, Result};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    // Add the frame rate and frame time to the window title
+    pub show_in_title: bool,
+    // How often the title is updated, in seconds
+    pub title_interval: f32,
+    // Frames the statistics in the title are taken over
+    pub window: usize,
+    // Write the time of every frame here
+    pub csv: Option<String>,
+    // Render this many frames, print a summary and exit
+    pub benchmark: Option<u32>,
+    // Frames rendered before the benchmark starts measuring, so shader compilation and the
+    // like don't skew the result
+    pub warmup: u32,
+}
+
+impl Default for StatsConfig {
+    fn default() -> StatsConfig {
+        StatsConfig {
+            show_in_title: false,
+            title_interval: 0.5,
+            window: 120,
+            csv: None,
+            benchmark: None,
+            warmup: 30,
+        }
+    }
+}
+
+// Frame time statistics, all times are in seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub frames: usize,
+    pub total: f32,
+    pub mean: f32,
+    pub min: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+impl Summary {
+    // None if there are no frames to summarize
+    pub fn from_frame_times(times: &[f32]) -> Option<Summary> {
+        if times.is_empty() {
+            return None;
+        }
+
+        let mut sorted = times.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let total: f32 = sorted.iter().sum();
+        Some(Summary {
+            frames: sorted.len(),
+            total,
+            mean: total / sorted.len() as f32,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            p50: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        })
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.total > 0.0 {
+            self.frames as f32 / self.total
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Frames:      {} in {:.2} s", self.frames, self.total)?;
+        writeln!(f, "Average:     {:.1} fps, {:.2} ms", self.fps(), self.mean * 1000.0)?;
+        writeln!(f, "Min / max:   {:.2} ms / {:.2} ms", self.min * 1000.0, self.max * 1000.0)?;
+        write!(f, "Percentiles: {:.2} ms (50%), {:.2} ms (95%), {:.2} ms (99%)",
+            self.p50 * 1000.0, self.p95 * 1000.0, self.p99 * 1000.0)
+    }
+}
+
+// Nearest rank percentile of sorted, non-empty times
+fn percentile(sorted: &[f32], percent: f32) -> f32 {
+    let rank = (percent / 100.0 * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// The times of the last few frames
+pub struct RollingWindow {
+    times: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl RollingWindow {
+    pub fn new(capacity: usize) -> RollingWindow {
+        RollingWindow {
+            times: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, frame_time: f32) {
+        if self.times.len() == self.capacity {
+            self.times.pop_front();
+        }
+        self.times.push_back(frame_time);
+    }
+
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    pub fn summary(&self) -> Option<Summary> {
+        let (front, back) = self.times.as_slices();
+        Summary::from_frame_times(&[front, back].concat())
+    }
+}
+
+// Writes one line per frame to a CSV file, with the frame number, the elapsed time of the app
+// and the frame time in milliseconds
+pub struct FrameLog {
+    writer: BufWriter<File>,
+    path: String,
+}
+
+impl FrameLog {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<FrameLog> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context(path.display())?;
+        }
+
+        let mut writer = BufWriter::new(File::create(path).context(path.display())?);
+        writeln!(writer, "frame,elapsed,frame_time_ms").context(path.display())?;
+        Ok(FrameLog { writer, path: path.display().to_string() })
+    }
+
+    pub fn log(&mut self, frame: u64, elapsed: f32, frame_time: f32) -> Result<()> {
+        writeln!(self.writer, "{},{:.6},{:.4}", frame, elapsed, frame_time * 1000.0)
+            .context(&self.path)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush().context(&self.path)
+    }
+}
+
+// Keeps the time of every frame after the warmup, until enough frames are measured
+pub struct Benchmark {
+    warmup: u32,
+    frames: u32,
+    seen: u32,
+    times: Vec<f32>,
+}
+
+impl Benchmark {
+    pub fn new(frames: u32, warmup: u32) -> Benchmark {
+        Benchmark {
+            warmup,
+            frames,
+            seen: 0,
+            times: Vec::with_capacity(frames as usize),
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.seen >= self.warmup && !self.is_done() {
+            self.times.push(frame_time);
+        }
+        self.seen += 1;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.times.len() >= self.frames as usize
+    }
+
+    // Frames the runner has to render for the benchmark to finish
+    pub fn total_frames(&self) -> u32 {
+        self.warmup + self.frames
+    }
+
+    pub fn summary(&self) -> Option<Summary> {
+        Summary::from_frame_times(&self.times)
+    }
+}
+
+// Everything the runner does with frame times. Frame times are measured on the wall clock, even
+// when the app is given a fixed timestep
+pub struct FrameStats {
+    window: RollingWindow,
+    log: Option<FrameLog>,
+    benchmark: Option<Benchmark>,
+    show_in_title: bool,
+    title_interval: f32,
+    since_title: f32,
+    frames: u64,
+}
+
+impl FrameStats {
+    pub fn new(config: &StatsConfig) -> Result<FrameStats> {
+        Ok(FrameStats {
+            window: RollingWindow::new(config.window),
+            log: config.csv.as_ref().map(FrameLog::create).transpose()?,
+            benchmark: config.benchmark.map(|frames| Benchmark::new(frames, config.warmup)),
+            show_in_title: config.show_in_title,
+            title_interval: config.title_interval,
+            since_title: 0.0,
+            frames: 0,
+        })
+    }
+
+    pub fn end_frame(&mut self, frame_time: f32, elapsed: f32) {
+        self.window.push(frame_time);
+        self.since_title += frame_time;
+        if let Some(benchmark) = &mut self.benchmark {
+            benchmark.record(frame_time);
+        }
+        if let Some(log) = &mut self.log {
+            if let Err(e) = log.log(self.frames, elapsed, frame_time) {
+                eprintln!("Frame time log stopped: {}", e);
+                self.log = None;
+            }
+        }
+        self.frames += 1;
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn summary(&self) -> Option<Summary> {
+        self.window.summary()
+    }
+
+    // The window title with the current frame rate, once every title_interval
+    pub fn title(&mut self, base: &str) -> Option<String> {
+        if !self.show_in_title || self.since_title < self.title_interval {
+            return None;
+        }
+        self.since_title = 0.0;
+        self.summary().map(|summary| {
+            format!("{} - {:.0} fps ({:.2} ms, max {:.2} ms)", base, summary.fps(), summary.mean * 1000.0, summary.max * 1000.0)
+        })
+    }
+
+    // Whether frame times should include waiting for the GPU, which matters when nothing else
+    // waits for it, like in headless mode
+    pub fn is_measuring(&self) -> bool {
+        self.log.is_some() || self.benchmark.is_some()
+    }
+
+    pub fn benchmark(&self) -> Option<&Benchmark> {
+        self.benchmark.as_ref()
+    }
+
+    pub fn is_benchmark_done(&self) -> bool {
+        self.benchmark.as_ref().is_some_and(Benchmark::is_done)
+    }
+
+    // Flush the log and print the benchmark results
+    pub fn finish(self) {
+        if let Some(log) = self.log {
+            let path = log.path().to_string();
+            match log.finish() {
+                Ok(()) => println!("Saved frame times to {}", path),
+                Err(e) => eprintln!("Failed to save frame times: {}", e),
+            }
+        }
+        if let Some(benchmark) = self.benchmark {
+            match benchmark.summary() {
+                Some(summary) if benchmark.is_done() => println!("Benchmark results:\n{}", summary),
+                Some(summary) => println!("Benchmark stopped early, results so far:\n{}", summary),
+                None => println!("Benchmark stopped before any frames were measured"),
+            }
+        }
+    }
+}
@@ -0,0 +1,613 @@
+// Capture the GL calls of one frame into a file, and replay them on another machine to compare
+// the result. Everything that goes through the GlBackend is traced, which covers gl_utils and the
+// runner. Calls an app makes to the gl functions directly are not.
+//
+// A trace holds every call from when the context was created up to the end of the captured
+// frame, since the frame needs the buffers, programs and state that were set up before it.
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fmt,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Context, Error, Result};
+use crate::gl_utils::{
+    backend::{self, BackendGuard, Fence, GlBackend, GlCall, TracingBackend},
+    capabilities::Capabilities,
+};
+
+// Bumped whenever GlCall or Trace change in a way that breaks old traces
+pub const TRACE_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TraceConfig {
+    pub enabled: bool,
+    // Which frame to capture, counting from 0
+    pub frame: u32,
+    pub output: String,
+}
+
+impl Default for TraceConfig {
+    fn default() -> TraceConfig {
+        TraceConfig {
+            enabled: false,
+            frame: 0,
+            output: "trace.bin".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trace {
+    // Size of the framebuffer the frame was drawn into
+    pub width: u32,
+    pub height: u32,
+    // The captured frame, and the index of its first call. Everything before sets it up
+    pub frame: u32,
+    pub frame_start: usize,
+    // Where it was captured, as GL_RENDERER and GL_VERSION
+    pub renderer: String,
+    pub gl_version: String,
+    pub calls: Vec<GlCall>,
+}
+
+impl Trace {
+    // Just the calls of the captured frame
+    pub fn frame_calls(&self) -> &[GlCall] {
+        &self.calls[self.frame_start.min(self.calls.len())..]
+    }
+
+    // The file starts with TRACE_VERSION, so old traces are rejected before they fail to decode
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let invalid = |e: bincode::Error| Error::Trace { path: path.display().to_string(), message: e.to_string() };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).context(parent.display())?;
+        }
+        let file = File::create(path).context(path.display())?;
+
+        let mut writer = BufWriter::new(file);
+        bincode::serialize_into(&mut writer, &TRACE_VERSION).map_err(invalid)?;
+        bincode::serialize_into(&mut writer, self).map_err(invalid)?;
+        writer.flush().context(path.display())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Trace> {
+        let path = path.as_ref();
+        let invalid = |message: String| Error::Trace { path: path.display().to_string(), message };
+        let file = File::open(path).context(path.display())?;
+
+        let mut reader = BufReader::new(file);
+        let version: u32 = bincode::deserialize_from(&mut reader).map_err(|e| invalid(e.to_string()))?;
+        if version != TRACE_VERSION {
+            return Err(invalid(format!("trace version {} is not supported, expected {}", version, TRACE_VERSION)));
+        }
+        bincode::deserialize_from(&mut reader).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+// Traces everything on this thread from when it's installed, and saves the trace once the
+// configured frame has been rendered. Has to be installed before the app creates anything
+pub struct FrameCapture {
+    tracer: Rc<TracingBackend>,
+    _guard: BackendGuard,
+    config: TraceConfig,
+    renderer: String,
+    gl_version: String,
+    frames: u32,
+    frame_start: usize,
+}
+
+impl FrameCapture {
+    pub fn install(config: &TraceConfig, capabilities: &Capabilities) -> FrameCapture {
+        let tracer = Rc::new(TracingBackend::new(backend::current()));
+        let guard = backend::install(tracer.clone());
+        FrameCapture {
+            tracer,
+            _guard: guard,
+            config: config.clone(),
+            renderer: capabilities.renderer.clone(),
+            gl_version: capabilities.version.clone(),
+            frames: 0,
+            frame_start: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        !self.tracer.is_recording()
+    }
+
+    // Call after every frame is rendered, with the size it was rendered at. Saves the trace after
+    // the captured frame and returns where it went
+    pub fn end_frame(&mut self, width: u32, height: u32) -> Result<Option<String>> {
+        if self.is_done() {
+            return Ok(None);
+        }
+        if self.frames < self.config.frame {
+            self.frames += 1;
+            self.frame_start = self.tracer.call_count();
+            return Ok(None);
+        }
+
+        self.tracer.stop();
+        let trace = Trace {
+            width,
+            height,
+            frame: self.frames,
+            frame_start: self.frame_start,
+            renderer: self.renderer.clone(),
+            gl_version: self.gl_version.clone(),
+            calls: self.tracer.calls(),
+        };
+        trace.save(&self.config.output)?;
+        Ok(Some(self.config.output.clone()))
+    }
+}
+
+// What replaying a trace ran into
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub calls: usize,
+    // Shaders that failed to compile, uniforms that weren't found and other differences from when
+    // the trace was captured
+    pub warnings: Vec<String>,
+    // Programs still alive at the end, as (traced id, replayed id)
+    pub programs: Vec<(GLuint, GLuint)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Object {
+    VertexArray,
+    Buffer,
+    Shader,
+    Program,
+    Query,
+    Framebuffer,
+    Renderbuffer,
+}
+
+impl Object {
+    // The kind of object a label's identifier names
+    fn from_identifier(identifier: GLenum) -> Option<Object> {
+        match identifier {
+            gl::VERTEX_ARRAY => Some(Object::VertexArray),
+            gl::BUFFER => Some(Object::Buffer),
+            gl::SHADER => Some(Object::Shader),
+            gl::PROGRAM => Some(Object::Program),
+            gl::QUERY => Some(Object::Query),
+            gl::FRAMEBUFFER => Some(Object::Framebuffer),
+            gl::RENDERBUFFER => Some(Object::Renderbuffer),
+            _ => None,
+        }
+    }
+}
+
+// Ids on the replaying side are whatever that driver hands out, so every id in the trace is
+// translated through this. Uniform locations are translated the same way, per program
+//
+// Framebuffer 0 is whatever framebuffer the caller had bound, so the frame ends up there
+#[derive(Default)]
+struct Translation {
+    ids: HashMap<(Object, GLuint), GLuint>,
+    locations: HashMap<(GLuint, GLint), GLint>,
+    fences: HashMap<usize, Fence>,
+    default_framebuffer: GLuint,
+}
+
+impl Translation {
+    fn id(&self, kind: Object, id: GLuint, warnings: &mut Vec<String>) -> GLuint {
+        if id == 0 {
+            return if kind == Object::Framebuffer { self.default_framebuffer } else { 0 };
+        }
+        self.ids.get(&(kind, id)).copied().unwrap_or_else(|| {
+            warnings.push(match kind {
+                // Traces from before framebuffers went through the backend don't have their setup
+                Object::Framebuffer => format!("framebuffer {} was used without being created, the trace is incomplete", id),
+                _ => format!("object {} was used without being created in the trace", id),
+            });
+            0
+        })
+    }
+
+    fn ids(&self, kind: Object, ids: &[GLuint], warnings: &mut Vec<String>) -> Vec<GLuint> {
+        ids.iter().map(|&id| self.id(kind, id, warnings)).collect()
+    }
+
+    fn created(&mut self, kind: Object, traced: &[GLuint], replayed: &[GLuint]) {
+        for (&traced, &replayed) in traced.iter().zip(replayed) {
+            self.ids.insert((kind, traced), replayed);
+        }
+    }
+
+    fn deleted(&mut self, kind: Object, ids: &[GLuint]) {
+        for id in ids {
+            self.ids.remove(&(kind, *id));
+        }
+    }
+}
+
+// Make every call in the trace again through the backend, which needs a current context with a
+// framebuffer of the trace's size bound. Frames drawn into a framebuffer of their own are copied
+// into the bound one at the end
+pub fn replay(calls: &[GlCall], gl: &dyn GlBackend) -> ReplayReport {
+    let mut translation = Translation {
+        default_framebuffer: gl.get_integer(gl::DRAW_FRAMEBUFFER_BINDING) as GLuint,
+        ..Translation::default()
+    };
+    let mut warnings = Vec::new();
+    // Followed in traced ids, to know what the frame ended up in and how big it is
+    let mut draw_framebuffer = 0;
+    let mut renderbuffer = 0;
+    let mut renderbuffer_sizes = HashMap::new();
+    let mut framebuffer_sizes = HashMap::new();
+
+    for call in calls {
+        match call {
+            GlCall::Enable(capability) => gl.enable(*capability),
+            GlCall::Disable(capability) => gl.disable(*capability),
+            GlCall::BlendFunc { source, destination } => gl.blend_func(*source, *destination),
+            GlCall::PolygonMode { face, mode } => gl.polygon_mode(*face, *mode),
+            GlCall::Viewport { x, y, width, height } => gl.viewport(*x, *y, *width, *height),
+            GlCall::ClearColor([r, g, b, a]) => gl.clear_color(*r, *g, *b, *a),
+            GlCall::Clear(mask) => gl.clear(*mask),
+            GlCall::Finish => gl.finish(),
+
+            GlCall::GenVertexArrays(traced) => {
+                let mut ids = vec![0; traced.len()];
+                gl.gen_vertex_arrays(&mut ids);
+                translation.created(Object::VertexArray, traced, &ids);
+            },
+            GlCall::DeleteVertexArrays(traced) => {
+                gl.delete_vertex_arrays(&translation.ids(Object::VertexArray, traced, &mut warnings));
+                translation.deleted(Object::VertexArray, traced);
+            },
+            GlCall::BindVertexArray(id) => gl.bind_vertex_array(translation.id(Object::VertexArray, *id, &mut warnings)),
+            GlCall::EnableVertexAttribArray(index) => gl.enable_vertex_attrib_array(*index),
+            GlCall::VertexAttribPointer { index, components, kind, normalized, stride, offset } => {
+                gl.vertex_attrib_pointer(*index, *components, *kind, *normalized, *stride, *offset);
+            },
+
+            GlCall::GenBuffers(traced) => {
+                let mut ids = vec![0; traced.len()];
+                gl.gen_buffers(&mut ids);
+                translation.created(Object::Buffer, traced, &ids);
+            },
+            GlCall::DeleteBuffers(traced) => {
+                gl.delete_buffers(&translation.ids(Object::Buffer, traced, &mut warnings));
+                translation.deleted(Object::Buffer, traced);
+            },
+            GlCall::BindBuffer { target, id } => gl.bind_buffer(*target, translation.id(Object::Buffer, *id, &mut warnings)),
+            GlCall::BufferData { target, data, usage } => gl.buffer_data(*target, data, *usage),
+            GlCall::AllocateBuffer { target, size, usage } => gl.allocate_buffer(*target, *size, *usage),
+
+            GlCall::GenFramebuffers(traced) => {
+                let mut ids = vec![0; traced.len()];
+                gl.gen_framebuffers(&mut ids);
+                translation.created(Object::Framebuffer, traced, &ids);
+            },
+            GlCall::DeleteFramebuffers(traced) => {
+                gl.delete_framebuffers(&translation.ids(Object::Framebuffer, traced, &mut warnings));
+                translation.deleted(Object::Framebuffer, traced);
+            },
+            GlCall::BindFramebuffer { target, id } => {
+                if *target != gl::READ_FRAMEBUFFER {
+                    draw_framebuffer = *id;
+                }
+                gl.bind_framebuffer(*target, translation.id(Object::Framebuffer, *id, &mut warnings));
+            },
+            GlCall::FramebufferRenderbuffer { target, attachment, renderbuffer: traced } => {
+                if *attachment == gl::COLOR_ATTACHMENT0 && *target != gl::READ_FRAMEBUFFER {
+                    if let Some(&size) = renderbuffer_sizes.get(traced) {
+                        framebuffer_sizes.insert(draw_framebuffer, size);
+                    }
+                }
+                gl.framebuffer_renderbuffer(*target, *attachment, translation.id(Object::Renderbuffer, *traced, &mut warnings));
+            },
+            GlCall::BlitFramebuffer { source, destination, mask, filter } => gl.blit_framebuffer(*source, *destination, *mask, *filter),
+            GlCall::GenRenderbuffers(traced) => {
+                let mut ids = vec![0; traced.len()];
+                gl.gen_renderbuffers(&mut ids);
+                translation.created(Object::Renderbuffer, traced, &ids);
+            },
+            GlCall::DeleteRenderbuffers(traced) => {
+                gl.delete_renderbuffers(&translation.ids(Object::Renderbuffer, traced, &mut warnings));
+                translation.deleted(Object::Renderbuffer, traced);
+            },
+            GlCall::BindRenderbuffer(id) => {
+                renderbuffer = *id;
+                gl.bind_renderbuffer(translation.id(Object::Renderbuffer, *id, &mut warnings));
+            },
+            GlCall::RenderbufferStorageMultisample { samples, format, width, height } => {
+                renderbuffer_sizes.insert(renderbuffer, (*width, *height));
+                gl.renderbuffer_storage_multisample(*samples, *format, *width, *height);
+            },
+
+            GlCall::PixelStore { parameter, value } => gl.pixel_store(*parameter, *value),
+            // The pixels weren't traced, so they are read and thrown away
+            GlCall::ReadPixels { x, y, width, height, format, kind } => {
+                let mut pixels = vec![0; backend::pixel_data_size(*width, *height, *format, *kind, gl.get_integer(gl::PACK_ALIGNMENT))];
+                gl.read_pixels(*x, *y, *width, *height, *format, *kind, &mut pixels);
+            },
+            GlCall::ReadPixelsToBuffer { x, y, width, height, format, kind, offset } => {
+                gl.read_pixels_to_buffer(*x, *y, *width, *height, *format, *kind, *offset);
+            },
+            GlCall::FenceSync(traced) => {
+                translation.fences.insert(*traced, gl.fence_sync());
+            },
+            GlCall::DeleteSync(traced) => match translation.fences.remove(traced) {
+                Some(fence) => gl.delete_sync(fence),
+                None => warnings.push(format!("fence 0x{:X} was deleted without being created in the trace", traced)),
+            },
+            // The callback belonged to the traced program, the replay can install its own
+            GlCall::DebugMessageCallback(_) => (),
+
+            GlCall::CreateShader { kind, id } => {
+                let shader = gl.create_shader(*kind);
+                translation.created(Object::Shader, &[*id], &[shader]);
+            },
+            GlCall::ShaderSource { shader, source } => {
+                let shader = translation.id(Object::Shader, *shader, &mut warnings);
+                match CString::new(source.as_str()) {
+                    Ok(source) => gl.shader_source(shader, &source),
+                    Err(_) => warnings.push(format!("shader {} has a nul byte in its source", shader)),
+                }
+            },
+            GlCall::CompileShader(traced) => {
+                let shader = translation.id(Object::Shader, *traced, &mut warnings);
+                gl.compile_shader(shader);
+                if gl.get_shader_iv(shader, gl::COMPILE_STATUS) != GLint::from(gl::TRUE) {
+                    warnings.push(format!("shader {} failed to compile:\n{}", traced, gl.shader_info_log(shader)));
+                }
+            },
+            GlCall::DeleteShader(traced) => {
+                gl.delete_shader(translation.id(Object::Shader, *traced, &mut warnings));
+                translation.deleted(Object::Shader, &[*traced]);
+            },
+
+            GlCall::CreateProgram(id) => {
+                let program = gl.create_program();
+                translation.created(Object::Program, &[*id], &[program]);
+            },
+            GlCall::AttachShader { program, shader } => gl.attach_shader(
+                translation.id(Object::Program, *program, &mut warnings),
+                translation.id(Object::Shader, *shader, &mut warnings),
+            ),
+            GlCall::LinkProgram(traced) => {
+                let program = translation.id(Object::Program, *traced, &mut warnings);
+                gl.link_program(program);
+                if gl.get_program_iv(program, gl::LINK_STATUS) != GLint::from(gl::TRUE) {
+                    warnings.push(format!("program {} failed to link:\n{}", traced, gl.program_info_log(program)));
+                }
+            },
+            GlCall::UseProgram(id) => gl.use_program(translation.id(Object::Program, *id, &mut warnings)),
+            GlCall::DeleteProgram(traced) => {
+                gl.delete_program(translation.id(Object::Program, *traced, &mut warnings));
+                translation.deleted(Object::Program, &[*traced]);
+            },
+            GlCall::GetUniformLocation { program, name, location } => {
+                let replayed = match CString::new(name.as_str()) {
+                    Ok(name) => gl.get_uniform_location(translation.id(Object::Program, *program, &mut warnings), &name),
+                    Err(_) => -1,
+                };
+                if replayed < 0 && *location >= 0 {
+                    warnings.push(format!("uniform \"{}\" of program {} was not found", name, program));
+                }
+                translation.locations.insert((*program, *location), replayed);
+            },
+            GlCall::Uniform { program, location, value } => {
+                match translation.locations.get(&(*program, *location)) {
+                    Some(&replayed) if replayed >= 0 => gl.uniform(replayed, *value),
+                    Some(_) => (),
+                    None => warnings.push(format!("uniform location {} of program {} was never looked up", location, program)),
+                }
+            },
+
+            GlCall::GenQueries(traced) => {
+                let mut ids = vec![0; traced.len()];
+                gl.gen_queries(&mut ids);
+                translation.created(Object::Query, traced, &ids);
+            },
+            GlCall::DeleteQueries(traced) => {
+                gl.delete_queries(&translation.ids(Object::Query, traced, &mut warnings));
+                translation.deleted(Object::Query, traced);
+            },
+            GlCall::QueryCounter { id, target } => gl.query_counter(translation.id(Object::Query, *id, &mut warnings), *target),
+
+            // Labels of objects that aren't traced, like framebuffers, are left out
+            GlCall::ObjectLabel { identifier, id, label } => {
+                if let Some(kind) = Object::from_identifier(*identifier) {
+                    gl.object_label(*identifier, translation.id(kind, *id, &mut warnings), label);
+                }
+            },
+            GlCall::PushDebugGroup(message) => gl.push_debug_group(message),
+            GlCall::PopDebugGroup => gl.pop_debug_group(),
+
+            GlCall::DrawElements { mode, count, kind, offset } => gl.draw_elements(*mode, *count, *kind, *offset),
+        }
+
+        if let Err(e) = gl.check_error("replay") {
+            warnings.push(format!("{} after {:?}", e, Summary(call)));
+        }
+    }
+
+    if draw_framebuffer != 0 {
+        match framebuffer_sizes.get(&draw_framebuffer) {
+            Some(&(width, height)) => {
+                gl.bind_framebuffer(gl::READ_FRAMEBUFFER, translation.id(Object::Framebuffer, draw_framebuffer, &mut warnings));
+                gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, translation.default_framebuffer);
+                gl.blit_framebuffer([0, 0, width, height], [0, 0, width, height], gl::COLOR_BUFFER_BIT, gl::NEAREST);
+                gl.bind_framebuffer(gl::FRAMEBUFFER, translation.default_framebuffer);
+                if let Err(e) = gl.check_error("replay") {
+                    warnings.push(format!("{} copying the frame out of framebuffer {}", e, draw_framebuffer));
+                }
+            },
+            None => warnings.push(format!("the frame ended in framebuffer {}, which has no color buffer in the trace", draw_framebuffer)),
+        }
+    }
+
+    let mut programs: Vec<(GLuint, GLuint)> = translation.ids.iter()
+        .filter(|((kind, _), _)| *kind == Object::Program)
+        .map(|((_, traced), &replayed)| (*traced, replayed))
+        .collect();
+    programs.sort_unstable();
+
+    ReplayReport { calls: calls.len(), warnings, programs }
+}
+
+// Prints a call without its buffer contents or shader source, which can be huge
+pub struct Summary<'a>(pub &'a GlCall);
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            GlCall::BufferData { target, data, usage } => {
+                write!(f, "BufferData {{ target: 0x{:04X}, data: <{} bytes>, usage: 0x{:04X} }}", target, data.len(), usage)
+            },
+            GlCall::ShaderSource { shader, source } => {
+                write!(f, "ShaderSource {{ shader: {}, source: <{} lines> }}", shader, source.lines().count())
+            },
+            call => write!(f, "{:?}", call),
+        }
+    }
+}
+
+impl fmt::Debug for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// The GL state that decides how a frame looks, read straight from the driver for comparing
+// replays on different machines
+#[derive(Debug)]
+pub struct GlState {
+    pub viewport: [GLint; 4],
+    pub clear_color: [f32; 4],
+    pub enabled: Vec<(&'static str, bool)>,
+    pub blend_func: (GLenum, GLenum),
+    pub polygon_mode: GLenum,
+    pub program: GLuint,
+    pub vertex_array: GLuint,
+    pub programs: Vec<ProgramState>,
+}
+
+#[derive(Debug)]
+pub struct ProgramState {
+    // The id in the trace, and in this context
+    pub traced: GLuint,
+    pub id: GLuint,
+    pub uniforms: Vec<(String, Vec<f32>)>,
+}
+
+const CAPABILITIES: [(&str, GLenum); 5] = [
+    ("GL_BLEND", gl::BLEND),
+    ("GL_CULL_FACE", gl::CULL_FACE),
+    ("GL_DEPTH_TEST", gl::DEPTH_TEST),
+    ("GL_MULTISAMPLE", gl::MULTISAMPLE),
+    ("GL_SCISSOR_TEST", gl::SCISSOR_TEST),
+];
+
+impl GlState {
+    // Needs a current context. Programs are (traced id, id) pairs, like in ReplayReport
+    pub fn query(programs: &[(GLuint, GLuint)]) -> GlState {
+        unsafe {
+            let integer = |parameter| {
+                let mut value = 0;
+                gl::GetIntegerv(parameter, &mut value);
+                value
+            };
+
+            let mut viewport = [0; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            let mut clear_color = [0.0; 4];
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+            // Some drivers return one value for both faces, others two
+            let mut polygon_mode = [0; 2];
+            gl::GetIntegerv(gl::POLYGON_MODE, polygon_mode.as_mut_ptr());
+
+            GlState {
+                viewport,
+                clear_color,
+                enabled: CAPABILITIES.iter().map(|&(name, capability)| (name, gl::IsEnabled(capability) == gl::TRUE)).collect(),
+                blend_func: (integer(gl::BLEND_SRC_RGB) as GLenum, integer(gl::BLEND_DST_RGB) as GLenum),
+                polygon_mode: polygon_mode[0] as GLenum,
+                program: integer(gl::CURRENT_PROGRAM) as GLuint,
+                vertex_array: integer(gl::VERTEX_ARRAY_BINDING) as GLuint,
+                programs: programs.iter().map(|&(traced, id)| ProgramState { traced, id, uniforms: uniforms(id) }).collect(),
+            }
+        }
+    }
+}
+
+// The current value of every active uniform outside of uniform blocks. Arrays only show their
+// first element
+unsafe fn uniforms(program: GLuint) -> Vec<(String, Vec<f32>)> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+
+    let mut uniforms = Vec::new();
+    for index in 0..count.max(0) as GLuint {
+        let mut name = [0u8; 256];
+        let (mut length, mut size, mut kind): (GLsizei, GLint, GLenum) = (0, 0, 0);
+        gl::GetActiveUniform(program, index, name.len() as GLsizei, &mut length, &mut size, &mut kind, name.as_mut_ptr() as *mut _);
+        let name = String::from_utf8_lossy(&name[..length.max(0) as usize]).into_owned();
+        let location = match CString::new(name.as_str()) {
+            Ok(c_name) => gl::GetUniformLocation(program, c_name.as_ptr()),
+            Err(_) => -1,
+        };
+        if location < 0 {
+            continue;
+        }
+
+        let components = match kind {
+            gl::FLOAT_VEC2 | gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 => 2,
+            gl::FLOAT_VEC3 | gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 => 3,
+            gl::FLOAT_VEC4 | gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 | gl::FLOAT_MAT2 => 4,
+            gl::FLOAT_MAT3 => 9,
+            gl::FLOAT_MAT4 => 16,
+            _ => 1,
+        };
+        let is_float = matches!(kind, gl::FLOAT | gl::FLOAT_VEC2 | gl::FLOAT_VEC3 | gl::FLOAT_VEC4
+            | gl::FLOAT_MAT2 | gl::FLOAT_MAT3 | gl::FLOAT_MAT4);
+        let values = if is_float {
+            let mut values = [0.0f32; 16];
+            gl::GetUniformfv(program, location, values.as_mut_ptr());
+            values[..components].to_vec()
+        } else {
+            let mut values = [0; 16];
+            gl::GetUniformiv(program, location, values.as_mut_ptr());
+            values[..components].iter().map(|&v| v as f32).collect()
+        };
+        uniforms.push((name, values));
+    }
+    uniforms
+}
+
+impl fmt::Display for GlState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Viewport:         {:?}", self.viewport)?;
+        writeln!(f, "Clear color:      {:?}", self.clear_color)?;
+        for (name, enabled) in &self.enabled {
+            writeln!(f, "{:<18}{}", format!("{}:", name), if *enabled { "enabled" } else { "disabled" })?;
+        }
+        writeln!(f, "Blend func:       0x{:04X}, 0x{:04X}", self.blend_func.0, self.blend_func.1)?;
+        writeln!(f, "Polygon mode:     0x{:04X}", self.polygon_mode)?;
+        writeln!(f, "Program:          {}", self.program)?;
+        writeln!(f, "Vertex array:     {}", self.vertex_array)?;
+        for program in &self.programs {
+            writeln!(f, "Program {} (replayed as {}):", program.traced, program.id)?;
+            for (name, values) in &program.uniforms {
+                writeln!(f, "    {} = {:?}", name, values)?;
+            }
+        }
+        Ok(())
+    }
+}
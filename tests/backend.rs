@@ -0,0 +1,307 @@
+// Checks the GL calls gl_utils makes, using a RecordingBackend instead of a context
+mod common;
+
+use std::rc::Rc;
+
+use gloom_rs::{
+    glm, gl,
+    error::{Error, ProgramError, ShaderError},
+    gl_utils::{
+        backend::{self, GlBackend, GlCall, RecordingBackend},
+        bindable::Bindable,
+        debug::{DebugOutput, DebugOutputConfig},
+        framebuffer::Framebuffer,
+        triangle::Triangle,
+        shaders::{
+            program::{Program, ProgramBuilder},
+            shader_type::ShaderType,
+            uniform::Uniform
+        }
+    },
+    scene::node::SceneNode,
+    screenshot::ScreenshotCapture
+};
+
+use common::recording;
+
+const VERTEX_SHADER: &str = "#version 430 core\nvoid main() {}";
+const FRAGMENT_SHADER: &str = "#version 430 core\nout vec4 color;\nvoid main() { color = vec4(1.0); }";
+
+fn linked_program() -> Program {
+    ProgramBuilder::new()
+        .compile_shader(VERTEX_SHADER, ShaderType::Vertex).unwrap()
+        .compile_shader(FRAGMENT_SHADER, ShaderType::Fragment).unwrap()
+        .link().unwrap()
+}
+
+#[test]
+fn triangle_uploads_buffers_and_deletes_them_on_drop() {
+    let (gl_calls, _guard) = recording();
+
+    let triangle = Triangle::init(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2]).unwrap();
+    assert_eq!(triangle.count, 3);
+
+    let calls = gl_calls.take_calls();
+    assert_eq!(calls[0], GlCall::GenVertexArrays(vec![1]));
+    assert_eq!(calls[2], GlCall::GenBuffers(vec![2, 3]));
+    let uploads: Vec<_> = calls.iter().filter_map(|call| match call {
+        GlCall::BufferData { target, data, usage } => Some((*target, data.len(), *usage)),
+        _ => None,
+    }).collect();
+    assert_eq!(uploads, vec![(gl::ARRAY_BUFFER, 9 * 4, gl::STATIC_DRAW), (gl::ELEMENT_ARRAY_BUFFER, 3 * 4, gl::STATIC_DRAW)]);
+    assert!(calls.contains(&GlCall::VertexAttribPointer {
+        index: 0, components: 3, kind: gl::FLOAT, normalized: false, stride: 12, offset: 0
+    }));
+    // Nothing is left bound
+    assert_eq!(calls.last(), Some(&GlCall::BindVertexArray(0)));
+
+    drop(triangle);
+    assert_eq!(gl_calls.take_calls(), vec![GlCall::DeleteBuffers(vec![2, 3]), GlCall::DeleteVertexArrays(vec![1])]);
+    assert_eq!(gl_calls.live_objects(), 0);
+}
+
+#[test]
+fn invalid_triangle_makes_no_calls() {
+    let (gl_calls, _guard) = recording();
+
+    assert!(Triangle::init(&[0.0, 0.0, 0.0, 1.0], &[0]).is_err());
+    assert!(Triangle::init(&[0.0, 0.0, 0.0], &[1]).is_err());
+    assert!(gl_calls.calls().is_empty());
+}
+
+#[test]
+fn failed_triangle_upload_deletes_buffers() {
+    let (gl_calls, _guard) = recording();
+    gl_calls.queue_error(gl::OUT_OF_MEMORY);
+
+    match Triangle::init(&[0.0; 9], &[0, 1, 2]) {
+        Err(Error::Gl(error)) => assert_eq!(error.code, gl::OUT_OF_MEMORY),
+        other => panic!("expected an out of memory error, got {:?}", other.err()),
+    }
+    assert_eq!(gl_calls.live_objects(), 0);
+}
+
+#[test]
+fn triangle_draw_binds_around_the_draw_call() {
+    let (gl_calls, _guard) = recording();
+    let triangle = Triangle::init(&[0.0; 9], &[0, 1, 2, 2, 1, 0]).unwrap();
+    gl_calls.take_calls();
+
+    triangle.draw();
+    assert_eq!(gl_calls.take_calls(), vec![
+        GlCall::BindVertexArray(1),
+        GlCall::BindBuffer { target: gl::ARRAY_BUFFER, id: 2 },
+        GlCall::BindBuffer { target: gl::ELEMENT_ARRAY_BUFFER, id: 3 },
+        GlCall::DrawElements { mode: gl::TRIANGLES, count: 6, kind: gl::UNSIGNED_INT, offset: 0 },
+        GlCall::BindBuffer { target: gl::ARRAY_BUFFER, id: 0 },
+        GlCall::BindBuffer { target: gl::ELEMENT_ARRAY_BUFFER, id: 0 },
+        GlCall::BindVertexArray(0),
+    ]);
+}
+
+#[test]
+fn linking_attaches_shaders_and_deletes_them() {
+    let (gl_calls, _guard) = recording();
+
+    let program = linked_program();
+    assert_eq!(program.program_id, 1);
+
+    let calls = gl_calls.take_calls();
+    assert_eq!(calls[0], GlCall::CreateProgram(1));
+    assert_eq!(calls[1], GlCall::CreateShader { kind: gl::VERTEX_SHADER, id: 2 });
+    assert_eq!(calls[2], GlCall::ShaderSource { shader: 2, source: VERTEX_SHADER.to_string() });
+    assert_eq!(calls[4], GlCall::CreateShader { kind: gl::FRAGMENT_SHADER, id: 3 });
+    assert_eq!(&calls[7..], &[
+        GlCall::AttachShader { program: 1, shader: 2 },
+        GlCall::AttachShader { program: 1, shader: 3 },
+        GlCall::LinkProgram(1),
+        GlCall::DeleteShader(2),
+        GlCall::DeleteShader(3),
+    ]);
+
+    drop(program);
+    assert_eq!(gl_calls.take_calls(), vec![GlCall::DeleteProgram(1)]);
+    assert_eq!(gl_calls.live_objects(), 0);
+}
+
+#[test]
+fn compile_errors_report_the_log_and_clean_up() {
+    let (gl_calls, _guard) = recording();
+    gl_calls.fail_compile("broken", "0:2(1): error: syntax error");
+
+    let result = ProgramBuilder::new()
+        .compile_shader(VERTEX_SHADER, ShaderType::Vertex).unwrap()
+        .compile_shader("#version 430 core\nbroken", ShaderType::Fragment);
+    match result {
+        Err(Error::Shader(ShaderError::Compile { log, .. })) => assert_eq!(log, "0:2(1): error: syntax error"),
+        other => panic!("expected a compile error, got {:?}", other.err()),
+    }
+
+    assert!(gl_calls.calls().iter().all(|call| !matches!(call, GlCall::LinkProgram(_))));
+    assert_eq!(gl_calls.live_objects(), 0);
+}
+
+#[test]
+fn link_errors_report_the_log_and_clean_up() {
+    let (gl_calls, _guard) = recording();
+    gl_calls.fail_link("error: no main function");
+
+    let result = ProgramBuilder::new()
+        .compile_shader(VERTEX_SHADER, ShaderType::Vertex).unwrap()
+        .link();
+    match result {
+        Err(Error::Program(ProgramError::Link { log })) => assert_eq!(log, "error: no main function"),
+        other => panic!("expected a link error, got {:?}", other.err().map(|e| e.to_string())),
+    }
+    assert_eq!(gl_calls.live_objects(), 0);
+}
+
+#[test]
+fn set_uniform_restores_the_program_in_use() {
+    let (gl_calls, _guard) = recording();
+    let mut program = linked_program();
+    program.locate_uniform("scale").unwrap();
+    program.locate_uniform("elapsed").unwrap();
+    let location = gl_calls.uniform_location(program.program_id, "elapsed").unwrap();
+
+    gl_calls.use_program(42);
+    gl_calls.take_calls();
+    program.set_uniform("elapsed", 1.5).unwrap();
+    assert_eq!(gl_calls.take_calls(), vec![
+        GlCall::UseProgram(program.program_id),
+        GlCall::Uniform { program: program.program_id, location, value: Uniform::Float(1.5) },
+        GlCall::UseProgram(42),
+    ]);
+}
+
+#[test]
+fn uniforms_have_to_be_located_first() {
+    let (gl_calls, _guard) = recording();
+    gl_calls.remove_uniform("missing");
+    let mut program = linked_program();
+    gl_calls.take_calls();
+
+    match program.locate_uniform("missing") {
+        Err(Error::Program(ProgramError::UniformNotFound(name))) => assert_eq!(name, "missing"),
+        other => panic!("expected the uniform to be missing, got {:?}", other.err().map(|e| e.to_string())),
+    }
+    assert!(program.set_uniform("elapsed", 1.0).is_err());
+    assert!(gl_calls.calls().is_empty());
+}
+
+#[test]
+fn failed_uniform_still_restores_the_program() {
+    let (gl_calls, _guard) = recording();
+    let mut program = linked_program();
+    program.locate_uniform("count").unwrap();
+    gl_calls.take_calls();
+
+    gl_calls.queue_error(gl::INVALID_OPERATION);
+    match program.set_uniform("count", 3) {
+        Err(Error::Program(ProgramError::Uniform { name, source })) => {
+            assert_eq!(name, "count");
+            assert_eq!(source.code, gl::INVALID_OPERATION);
+        },
+        other => panic!("expected a uniform error, got {:?}", other.err().map(|e| e.to_string())),
+    }
+    assert_eq!(gl_calls.calls().last(), Some(&GlCall::UseProgram(0)));
+}
+
+#[test]
+fn scene_nodes_set_their_transform_before_drawing() {
+    let (gl_calls, _guard) = recording();
+    let mesh = Rc::new(Triangle::init(&[0.0; 9], &[0, 1, 2]).unwrap());
+    let mut program = linked_program();
+    program.locate_uniform("c_trans").unwrap();
+    let program = Rc::new(program);
+
+    let mut node = SceneNode::with_mesh("mesh", mesh, Rc::clone(&program));
+    node.position = glm::vec3(1.0, 2.0, 3.0);
+    node.update_world_transforms(&glm::identity());
+    let view = glm::translation(&glm::vec3(0.0, 0.0, -5.0));
+    gl_calls.take_calls();
+
+    node.draw(&view).unwrap();
+    let calls = gl_calls.take_calls();
+    let expected = view * glm::translation(&glm::vec3(1.0, 2.0, 3.0));
+    let location = gl_calls.uniform_location(program.program_id, "c_trans").unwrap();
+    assert_eq!(calls[1], GlCall::Uniform { program: program.program_id, location, value: Uniform::Mat4(expected) });
+    let draw = calls.iter().position(|call| matches!(call, GlCall::DrawElements { count: 3, .. })).unwrap();
+    assert!(calls[..draw].contains(&GlCall::UseProgram(program.program_id)));
+}
+
+#[test]
+fn multisampled_framebuffer_is_resolved_before_reading() {
+    let (gl_calls, _guard) = recording();
+    let framebuffer = Framebuffer::new(4, 2, 4).unwrap();
+    let calls = gl_calls.take_calls();
+    let storage: Vec<i32> = calls.iter().filter_map(|call| match call {
+        GlCall::RenderbufferStorageMultisample { samples, width: 4, height: 2, .. } => Some(*samples),
+        _ => None,
+    }).collect();
+    // Color and depth for the draw target, then for the resolve target
+    assert_eq!(storage, [4, 4, 0, 0]);
+    assert_eq!(calls.last(), Some(&GlCall::BindFramebuffer { target: gl::FRAMEBUFFER, id: 0 }));
+
+    framebuffer.bind();
+    gl_calls.take_calls();
+    let image = framebuffer.read_pixels().unwrap();
+    assert_eq!(image.dimensions(), (4, 2));
+    let calls = gl_calls.take_calls();
+    let blit = calls.iter().position(|call| matches!(call, GlCall::BlitFramebuffer { .. })).unwrap();
+    let read = calls.iter().position(|call| matches!(call, GlCall::ReadPixels { width: 4, height: 2, .. })).unwrap();
+    assert!(blit < read);
+    // The framebuffer that was bound before is bound again
+    assert_eq!(calls.last(), Some(&GlCall::BindFramebuffer { target: gl::FRAMEBUFFER, id: 1 }));
+
+    drop(framebuffer);
+    assert_eq!(gl_calls.live_objects(), 0);
+}
+
+#[test]
+fn screenshots_read_back_through_a_pixel_buffer() {
+    let (gl_calls, _guard) = recording();
+    let directory = std::env::temp_dir().join(format!("gloom-backend-screenshot-{}", std::process::id()));
+    let path = directory.join("shot.png");
+    let mut capture = ScreenshotCapture::new(&directory, false);
+    capture.capture_to(3, 2, path.clone()).unwrap();
+
+    let calls = gl_calls.calls();
+    assert!(calls.contains(&GlCall::AllocateBuffer { target: gl::PIXEL_PACK_BUFFER, size: 3 * 2 * 4, usage: gl::STREAM_READ }));
+    assert!(calls.contains(&GlCall::ReadPixelsToBuffer {
+        x: 0, y: 0, width: 3, height: 2, format: gl::RGBA, kind: gl::UNSIGNED_BYTE, offset: 0
+    }));
+    assert!(matches!(calls.last(), Some(GlCall::FenceSync(_))));
+
+    capture.finish();
+    drop(capture);
+    assert!(gl_calls.calls().iter().any(|call| matches!(call, GlCall::DeleteSync(_))));
+    assert_eq!(gl_calls.live_objects(), 0);
+    assert_eq!(image::open(&path).unwrap().to_rgba().dimensions(), (3, 2));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn debug_output_removes_its_callback_on_drop() {
+    let (gl_calls, _guard) = recording();
+    let output = DebugOutput::install(DebugOutputConfig::default());
+    assert_eq!(gl_calls.take_calls(), [
+        GlCall::Enable(gl::DEBUG_OUTPUT),
+        GlCall::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS),
+        GlCall::DebugMessageCallback(true),
+    ]);
+    drop(output);
+    assert_eq!(gl_calls.take_calls(), [GlCall::DebugMessageCallback(false)]);
+}
+
+#[test]
+fn installed_backend_is_restored_by_the_guard() {
+    let recording = Rc::new(RecordingBackend::new());
+    let before = backend::current();
+    {
+        let _guard = backend::install(recording.clone());
+        let current = backend::current();
+        assert!(Rc::ptr_eq(&current, &(recording.clone() as Rc<dyn GlBackend>)));
+    }
+    assert!(Rc::ptr_eq(&backend::current(), &before));
+}
@@ -0,0 +1,276 @@
+// Golden image testing: render a scene headlessly and compare it against a reference PNG in
+// tests/golden. On failure the rendered image and a diff are written to target/golden.
+//
+// Set GLOOM_UPDATE_GOLDEN=1 to overwrite the references with what is rendered instead, then
+// look over the new images before committing them.
+//
+// Also holds the helpers the other test files share, so not every file uses everything.
+#![allow(dead_code)]
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use gloom_rs::{
+    glm,
+    app::{App, AppContext},
+    config::Config,
+    gl_utils::{
+        backend::{self, RecordingBackend},
+        bindable::Bindable,
+        debug::DebugMode,
+        triangle::Triangle,
+        shaders::program::{Program, ProgramBuilder}
+    },
+    headless::HeadlessContext,
+    input::actions::Actions,
+    runner,
+};
+use image::{Rgba, RgbaImage};
+
+pub const UPDATE_ENV: &str = "GLOOM_UPDATE_GOLDEN";
+
+// Every golden test renders at this size, so references stay small
+pub const WIDTH: u32 = 128;
+pub const HEIGHT: u32 = 96;
+
+// Tests run in parallel, but one context at a time is plenty for the software rasterizer
+static RENDER_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    // Pixels where no channel differs by more than this are equal
+    pub channel: u8,
+    // Pixels over the channel tolerance still pass if the perceived color difference is below
+    // this, from 0 for identical to 1 for the largest possible difference
+    pub perceptual: f32,
+    // Fraction of pixels that may fail before the whole image does, for rasterization
+    // differences along edges
+    pub max_failing: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            channel: 2,
+            perceptual: 0.002,
+            max_failing: 0.001,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    pub failing_pixels: usize,
+    pub total_pixels: usize,
+    // Largest perceptual difference found
+    pub max_difference: f32,
+    // The reference faded to grey, with pixels over the channel tolerance in yellow and failing
+    // pixels in red
+    pub diff: RgbaImage,
+}
+
+impl Comparison {
+    pub fn failing_fraction(&self) -> f32 {
+        self.failing_pixels as f32 / self.total_pixels.max(1) as f32
+    }
+}
+
+// Compare two images of the same size
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: Tolerance) -> Comparison {
+    assert_eq!(actual.dimensions(), expected.dimensions(), "images have to be the same size to compare");
+
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    let mut failing_pixels = 0;
+    let mut max_difference: f32 = 0.0;
+    for ((a, e), d) in actual.pixels().zip(expected.pixels()).zip(diff.pixels_mut()) {
+        let channel_difference = a.0.iter().zip(e.0.iter()).map(|(&a, &e)| a.abs_diff(e)).max().unwrap_or(0);
+        if channel_difference <= tolerance.channel {
+            *d = faded(e);
+            continue;
+        }
+
+        let difference = perceptual_difference(a, e);
+        max_difference = max_difference.max(difference);
+        if difference <= tolerance.perceptual {
+            *d = Rgba([255, 255, 0, 255]);
+        } else {
+            *d = Rgba([255, 0, 0, 255]);
+            failing_pixels += 1;
+        }
+    }
+
+    Comparison {
+        failing_pixels,
+        total_pixels: expected.pixels().len(),
+        max_difference,
+        diff,
+    }
+}
+
+// Color difference in YIQ space, weighted for how sensitive the eye is to each component, from
+// "Measuring perceived color difference using YIQ NTSC transmission color space in mobile
+// applications" by Kotsarenko and Ramos. Alpha is blended against white first
+fn perceptual_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let yiq = |pixel: &Rgba<u8>| {
+        let alpha = pixel[3] as f32 / 255.0;
+        let blend = |c: u8| 255.0 + (c as f32 - 255.0) * alpha;
+        let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+        (
+            r * 0.2988953 + g * 0.5866225 + b * 0.1144822,
+            r * 0.595978 - g * 0.2741761 - b * 0.3218019,
+            r * 0.2114702 - g * 0.5226171 + b * 0.3111469,
+        )
+    };
+    let (ya, ia, qa) = yiq(a);
+    let (yb, ib, qb) = yiq(b);
+    let delta = 0.5053 * (ya - yb).powi(2) + 0.299 * (ia - ib).powi(2) + 0.1957 * (qa - qb).powi(2);
+    // The largest possible delta, so the result is between 0 and 1
+    delta / 35215.0
+}
+
+fn faded(pixel: &Rgba<u8>) -> Rgba<u8> {
+    let luma = (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000;
+    let grey = (255 - (255 - luma) / 4) as u8;
+    Rgba([grey, grey, grey, 255])
+}
+
+// A config for rendering one frame at the golden size at the given time. OpenGL errors panic,
+// so they fail the test instead of just being printed
+pub fn golden_config(elapsed: f32) -> Config {
+    let mut config = Config::default();
+    config.window.width = WIDTH;
+    config.window.height = HEIGHT;
+    config.headless.frames = 1;
+    config.headless.start_time = elapsed;
+    config.debug_output.mode = DebugMode::Break;
+    config
+}
+
+// Render the app headlessly. None if this machine can't create a headless context, in which case
+// the test should be skipped
+pub fn render<A: App, F: FnOnce() -> A>(config: &Config, create_app: F) -> Option<RgbaImage> {
+    let _lock = RENDER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Test for a context up front, so a missing driver skips instead of failing
+    match HeadlessContext::new(false) {
+        Ok(context) => drop(context),
+        Err(e) => {
+            eprintln!("Skipping golden test, no headless context: {}", e);
+            return None;
+        }
+    }
+
+    Some(runner::render_headless(config, create_app).unwrap_or_else(|e| panic!("Rendering failed: {}", e)))
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name))
+}
+
+// Record GL calls instead of making them until the guard is dropped
+pub fn recording() -> (Rc<RecordingBackend>, backend::BackendGuard) {
+    let recording = Rc::new(RecordingBackend::new());
+    let guard = backend::install(recording.clone());
+    (recording, guard)
+}
+
+// Where tests write their files: target/<directory>/<name>, with the directory created
+pub fn output_path(directory: &str, name: &str) -> PathBuf {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("target").join(directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory.join(name)
+}
+
+// Compare the image with tests/golden/<name>.png and panic with a summary if they differ
+pub fn assert_golden(name: &str, actual: &RgbaImage, tolerance: Tolerance) {
+    let reference = reference_path(name);
+    if std::env::var_os(UPDATE_ENV).is_some_and(|value| value != "0") {
+        actual.save(&reference).unwrap_or_else(|e| panic!("Failed to write {}: {}", reference.display(), e));
+        eprintln!("Updated golden image {}", reference.display());
+        return;
+    }
+
+    let save_output = |kind: &str, image: &RgbaImage| {
+        let path = output_path("golden", &format!("{}.{}.png", name, kind));
+        image.save(&path).unwrap_or_else(|e| panic!("Failed to write {}: {}", path.display(), e));
+        path
+    };
+
+    let expected = match image::open(&reference) {
+        Ok(expected) => expected.to_rgba(),
+        Err(e) => {
+            let actual_path = save_output("actual", actual);
+            panic!("Failed to load golden image {}: {}\nRendered image saved to {}, run with {}=1 to use it as the reference",
+                reference.display(), e, actual_path.display(), UPDATE_ENV);
+        }
+    };
+
+    if actual.dimensions() != expected.dimensions() {
+        let actual_path = save_output("actual", actual);
+        panic!("{} is {:?} but the golden image is {:?}, rendered image saved to {}",
+            name, actual.dimensions(), expected.dimensions(), actual_path.display());
+    }
+
+    let comparison = compare(actual, &expected, tolerance);
+    if comparison.failing_fraction() > tolerance.max_failing {
+        let actual_path = save_output("actual", actual);
+        let diff_path = save_output("diff", &comparison.diff);
+        panic!("{} differs from its golden image in {} of {} pixels ({:.3}%, allowed {:.3}%), largest difference {:.4}\n\
+                Rendered: {}\nDiff:     {}\nRun with {}=1 to accept the new image",
+            name, comparison.failing_pixels, comparison.total_pixels,
+            comparison.failing_fraction() * 100.0, tolerance.max_failing * 100.0, comparison.max_difference,
+            actual_path.display(), diff_path.display(), UPDATE_ENV);
+    }
+}
+
+// A quad covering the whole framebuffer, drawn with main.vert and the given fragment shader
+pub struct FullscreenApp {
+    fragment_shader: &'static str,
+    quad: Option<Triangle>,
+    program: Option<Program>,
+}
+
+impl FullscreenApp {
+    pub fn new(fragment_shader: &'static str) -> FullscreenApp {
+        FullscreenApp { fragment_shader, quad: None, program: None }
+    }
+}
+
+impl App for FullscreenApp {
+    fn init(&mut self, _ctx: &AppContext) -> Result<(), Box<dyn Error>> {
+        let vertices = [
+            -1.0, -1.0, 0.0,
+             1.0, -1.0, 0.0,
+             1.0,  1.0, 0.0,
+            -1.0,  1.0, 0.0,
+        ];
+        self.quad = Some(Triangle::init(&vertices, &[0, 1, 2, 0, 2, 3])?);
+
+        let mut program = ProgramBuilder::new()
+            .attach_file("assets/shaders/main.vert")?
+            .attach_file(self.fragment_shader)?
+            .link()?;
+        let identity = glm::identity::<f32, glm::U4>();
+        for uniform in ["projection", "c_trans"].iter() {
+            program.locate_uniform(uniform)?;
+            program.set_uniform(uniform, identity)?;
+        }
+        // Not every fragment shader uses the time
+        let _ = program.locate_uniform("elapsed");
+        self.program = Some(program);
+
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &AppContext, _delta_time: f32, _actions: &Actions) { }
+
+    fn render(&mut self, ctx: &AppContext) {
+        let (quad, program) = (self.quad.as_ref().unwrap(), self.program.as_ref().unwrap());
+        let _ = program.set_uniform("elapsed", ctx.elapsed);
+        program.bind();
+        quad.draw();
+        program.unbind();
+    }
+}
@@ -0,0 +1,66 @@
+// Command line parsing, without rendering anything
+mod common;
+
+use gloom_rs::{
+    config::{Command, Config, ConfigError},
+    error::Error,
+    gl_utils::shaders::program::ProgramBuilder
+};
+
+use common::{output_path, recording};
+
+fn parse(args: &[&str]) -> Result<Command, ConfigError> {
+    Config::from_args(args.iter().map(|arg| arg.to_string()))
+}
+
+fn run(args: &[&str]) -> Config {
+    match parse(args) {
+        Ok(Command::Run(config)) => *config,
+        other => panic!("expected a config, got {:?}", other),
+    }
+}
+
+#[test]
+fn flag_values_are_never_read_as_flags() {
+    let config = run(&["--title", "--help", "--bindings", "--config"]);
+    assert_eq!(config.window.title, "--help");
+    assert_eq!(config.input.bindings, "--config");
+
+    assert!(matches!(parse(&["--width", "800", "-h"]), Ok(Command::Help)));
+    assert!(matches!(parse(&["--help", "--no-such-flag"]), Ok(Command::Help)));
+}
+
+#[test]
+fn config_file_is_loaded_before_the_overrides() {
+    let path = output_path("config", "small.toml");
+    std::fs::write(&path, "[window]\nwidth = 100\nheight = 50\n").unwrap();
+    let path = path.display().to_string();
+
+    // Overrides win wherever they are on the command line
+    let config = run(&["--width", "300", "--config", &path]);
+    assert_eq!((config.window.width, config.window.height), (300, 50));
+
+    assert!(matches!(parse(&["--config"]), Err(ConfigError::MissingValue(flag)) if flag == "--config"));
+}
+
+#[test]
+fn bad_values_are_reported_before_the_config_is_loaded() {
+    let missing = output_path("config", "missing.toml").display().to_string();
+    let e = parse(&["--config", &missing, "--msaa", "lots"]).unwrap_err();
+    assert!(matches!(&e, ConfigError::BadValue(flag, value) if flag == "--msaa" && value == "lots"), "{}", e);
+
+    assert!(matches!(parse(&["--config", &missing]), Err(ConfigError::Io(..))));
+    assert!(matches!(parse(&["--frames"]), Err(ConfigError::MissingValue(_))));
+}
+
+#[test]
+fn missing_shaders_are_reported_when_loaded() {
+    // Apps that never load the configured shaders shouldn't care whether they exist
+    let path = output_path("config", "shaders.toml");
+    std::fs::write(&path, "[renderer]\nshaders = [\"no/such/shader.vert\"]\n").unwrap();
+    let config = run(&["--config", &path.display().to_string()]);
+
+    let (_gl_calls, _guard) = recording();
+    let e = ProgramBuilder::new().attach_file(&config.renderer.shaders[0]).map(drop).unwrap_err();
+    assert!(matches!(&e, Error::Io { path, .. } if path == "no/such/shader.vert"), "{}", e);
+}
@@ -0,0 +1,202 @@
+// Object labels, debug groups and how they show up in debug messages
+mod common;
+
+use std::{ffi::CString, rc::Rc};
+
+use gloom_rs::{
+    gl, glm,
+    gl_utils::{
+        backend::{self, GlCall, NativeGl, RecordingBackend},
+        debug::{self, DebugMessage, DebugMode, DebugOutput, DebugOutputConfig, DebugSeverity, DebugSource, DebugType},
+        shaders::program::ProgramBuilder,
+        triangle::Triangle
+    },
+    headless::HeadlessContext,
+    scene::node::SceneNode
+};
+
+// Labels are per thread and every test runs on its own, so they don't get in each other's way
+fn recording() -> (Rc<RecordingBackend>, backend::BackendGuard) {
+    debug::enable_labels(true);
+    common::recording()
+}
+
+fn labels(calls: &[GlCall]) -> Vec<(u32, String)> {
+    labeled(calls).into_iter().map(|(identifier, _, label)| (identifier, label)).collect()
+}
+
+fn labeled(calls: &[GlCall]) -> Vec<(u32, u32, String)> {
+    calls.iter()
+        .filter_map(|call| match call {
+            GlCall::ObjectLabel { identifier, id, label } => Some((*identifier, *id, label.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn meshes_are_labeled_after_their_node() {
+    let (gl_calls, _guard) = recording();
+    let mesh = Rc::new(Triangle::init(&[0.0; 9], &[0, 1, 2]).unwrap());
+    let calls = gl_calls.take_calls();
+    assert_eq!(labels(&calls), [
+        (gl::VERTEX_ARRAY, "mesh of 1 triangles".to_string()),
+        (gl::BUFFER, "mesh of 1 triangles vertices".to_string()),
+        (gl::BUFFER, "mesh of 1 triangles indices".to_string()),
+    ]);
+    let ids: Vec<(u32, u32)> = labeled(&calls).into_iter().map(|(identifier, id, _)| (identifier, id)).collect();
+
+    let program = Rc::new(ProgramBuilder::new().link().unwrap());
+    let node = SceneNode::with_mesh("floor", Rc::clone(&mesh), program);
+    assert_eq!(debug::object_label(ids[0].0, ids[0].1).as_deref(), Some("floor"));
+    assert_eq!(debug::object_label(ids[1].0, ids[1].1).as_deref(), Some("floor vertices"));
+
+    // A new object with the same id shouldn't inherit the label
+    drop(node);
+    drop(mesh);
+    assert!(ids.iter().all(|&(identifier, id)| debug::object_label(identifier, id).is_none()));
+}
+
+#[test]
+fn programs_are_labeled_after_their_shader_files() {
+    let (gl_calls, _guard) = recording();
+    let program = ProgramBuilder::new()
+        .attach_file("assets/shaders/main.vert").unwrap()
+        .attach_file("assets/shaders/main.frag").unwrap()
+        .link().unwrap();
+
+    let labels = labels(&gl_calls.calls());
+    assert_eq!(labels, [
+        (gl::SHADER, "assets/shaders/main.vert".to_string()),
+        (gl::SHADER, "assets/shaders/main.frag".to_string()),
+        (gl::PROGRAM, "main.vert + main.frag".to_string()),
+    ]);
+    // The shaders are deleted at link, so only the program keeps its label
+    let program_id = program.program_id;
+    assert_eq!(debug::object_label(gl::PROGRAM, program_id).as_deref(), Some("main.vert + main.frag"));
+    drop(program);
+    assert_eq!(debug::object_label(gl::PROGRAM, program_id), None);
+}
+
+#[test]
+fn scene_nodes_are_drawn_in_nested_groups() {
+    let (gl_calls, _guard) = recording();
+    let mesh = Rc::new(Triangle::init(&[0.0; 9], &[0, 1, 2]).unwrap());
+    let mut program = ProgramBuilder::new().link().unwrap();
+    program.locate_uniform("c_trans").unwrap();
+    let program = Rc::new(program);
+
+    let mut root = SceneNode::new("root");
+    root.add_child(SceneNode::with_mesh("floor", mesh, program));
+    gl_calls.take_calls();
+    {
+        let _render = debug::group("render");
+        root.draw(&glm::identity()).unwrap();
+    }
+
+    let groups: Vec<GlCall> = gl_calls.take_calls().into_iter()
+        .filter(|call| matches!(call, GlCall::PushDebugGroup(_) | GlCall::PopDebugGroup | GlCall::DrawElements { .. }))
+        .map(|call| match call {
+            GlCall::DrawElements { .. } => GlCall::DrawElements { mode: 0, count: 0, kind: 0, offset: 0 },
+            call => call,
+        })
+        .collect();
+    assert_eq!(groups, [
+        GlCall::PushDebugGroup("render".to_string()),
+        GlCall::PushDebugGroup("root".to_string()),
+        GlCall::PushDebugGroup("floor".to_string()),
+        GlCall::DrawElements { mode: 0, count: 0, kind: 0, offset: 0 },
+        GlCall::PopDebugGroup,
+        GlCall::PopDebugGroup,
+        GlCall::PopDebugGroup,
+    ]);
+}
+
+#[test]
+fn disabled_labels_make_no_calls() {
+    let (gl_calls, _guard) = recording();
+    debug::enable_labels(false);
+    let mesh = Triangle::init(&[0.0; 9], &[0, 1, 2]).unwrap();
+    mesh.set_label("ignored");
+    drop(debug::group("ignored"));
+
+    assert!(gl_calls.calls().iter().all(|call| !matches!(call,
+        GlCall::ObjectLabel { .. } | GlCall::PushDebugGroup(_) | GlCall::PopDebugGroup)));
+}
+
+#[test]
+fn messages_are_annotated_with_labels() {
+    let lookup = |identifier, id| match (identifier, id) {
+        (gl::BUFFER, 3) => Some("floor vertices".to_string()),
+        (gl::PROGRAM, 5) => Some("main.vert + main.frag".to_string()),
+        _ => None,
+    };
+
+    assert_eq!(
+        debug::annotate_with("Buffer object 3 (bound to GL_ARRAY_BUFFER_ARB, usage hint is GL_STATIC_DRAW)", lookup),
+        "Buffer object 3 \"floor vertices\" (bound to GL_ARRAY_BUFFER_ARB, usage hint is GL_STATIC_DRAW)"
+    );
+    assert_eq!(
+        debug::annotate_with("GL_INVALID_OPERATION in glUseProgram(program 5 not linked)", lookup),
+        "GL_INVALID_OPERATION in glUseProgram(program 5 \"main.vert + main.frag\" not linked)"
+    );
+    // Unknown objects and numbers that aren't ids are left alone
+    let untouched = "buffer object 4 is 3 bytes, program 7 failed";
+    assert_eq!(debug::annotate_with(untouched, lookup), untouched);
+}
+
+#[test]
+fn messages_mention_their_groups() {
+    let message = DebugMessage {
+        id: 1,
+        source: DebugSource::Application,
+        kind: DebugType::Marker,
+        severity: DebugSeverity::Notification,
+        message: "hello".to_string(),
+        groups: vec!["render".to_string(), "floor".to_string()],
+    };
+    assert_eq!(message.to_string(), "[Notification Application Marker #1] hello (in render > floor)");
+}
+
+#[test]
+fn driver_messages_carry_labels_and_groups() {
+    let context = match HeadlessContext::new(true) {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("Skipping, no headless context: {}", e);
+            return;
+        },
+    };
+    debug::enable_labels(true);
+    let config = DebugOutputConfig {
+        mode: DebugMode::Collect,
+        min_severity: DebugSeverity::Notification,
+        types: vec![DebugType::Marker],
+        deduplicate: false,
+        ..DebugOutputConfig::default()
+    };
+    let output = DebugOutput::install(config);
+
+    let mut buffer = 0;
+    unsafe { gl::CreateBuffers(1, &mut buffer) };
+    debug::label(&NativeGl, gl::BUFFER, buffer, "floor vertices");
+    let text = CString::new(format!("checking buffer {}", buffer)).unwrap();
+    {
+        let _render = debug::group("render");
+        let _floor = debug::group("floor");
+        unsafe {
+            gl::DebugMessageInsert(gl::DEBUG_SOURCE_APPLICATION, gl::DEBUG_TYPE_MARKER, 7,
+                gl::DEBUG_SEVERITY_NOTIFICATION, -1, text.as_ptr());
+        }
+    }
+
+    let messages = output.take_collected();
+    unsafe { gl::DeleteBuffers(1, &buffer) };
+    debug::forget_label(gl::BUFFER, buffer);
+    drop(output);
+    drop(context);
+
+    let marker = messages.iter().find(|message| message.id == 7).expect("the marker should be collected");
+    assert_eq!(marker.message, format!("checking buffer {} \"floor vertices\"", buffer));
+    assert_eq!(marker.groups, ["render", "floor"]);
+}
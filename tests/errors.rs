@@ -0,0 +1,86 @@
+// Config, bindings and display errors keep their cause as the source, and convert into the crate's Error
+mod common;
+
+use std::error::Error as _;
+
+use gloom_rs::{
+    config::{Config, ConfigError},
+    crash::{self, CrashReport},
+    display::DisplayError,
+    error::{self, Context, Error},
+    input::bindings::{Bindings, BindingsError}
+};
+
+use common::output_path;
+
+#[test]
+fn config_errors_keep_the_cause_as_their_source() {
+    let path = output_path("errors", "broken.toml");
+    std::fs::write(&path, "[window\n").unwrap();
+
+    let e = Config::load(&path).unwrap_err();
+    assert!(matches!(e, ConfigError::Parse(..)));
+    assert_eq!(e.to_string(), format!("Failed to parse config file {}", path.display()));
+    assert!(e.source().is_some());
+
+    let e = Error::from(e);
+    assert!(matches!(e, Error::Config(_)));
+    let chained = error::chain(&e);
+    assert!(chained.starts_with(&format!("Failed to parse config file {}: ", path.display())), "{}", chained);
+
+    let e = Config::load(output_path("errors", "missing.toml")).unwrap_err();
+    assert!(matches!(e, ConfigError::Io(..)));
+    assert!(e.source().unwrap().downcast_ref::<std::io::Error>().is_some());
+}
+
+#[test]
+fn bindings_and_display_errors_convert_into_the_crate_error() {
+    let e = Bindings::from_toml("actions = 3").unwrap_err();
+    assert!(matches!(e, BindingsError::Parse(_)));
+    assert!(e.source().is_some());
+
+    // Misspelled sections and keys aren't silently ignored, while the shipped file still loads
+    assert!(Bindings::load("assets/config/bindings.toml").is_ok());
+    assert!(matches!(Bindings::from_toml("[action]\njump = [\"Space\"]"), Err(BindingsError::Parse(_))));
+    assert!(matches!(Bindings::from_toml("[axes.move_up]\npositiv = [\"E\"]"), Err(BindingsError::Parse(_))));
+
+    // A directory can't be read as a file
+    let directory = output_path("errors", "bindings");
+    std::fs::create_dir_all(&directory).unwrap();
+    let e = Bindings::load(&directory).unwrap_err();
+    assert_eq!(e.to_string(), format!("Failed to read bindings file {}", directory.display()));
+
+    let e = Bindings::from_toml("[actions]\njump = [\"NoSuchKey\"]").unwrap_err();
+    assert!(e.source().is_none());
+    let e: Error = e.into();
+    assert_eq!(error::chain(&e), "Unknown key or mouse button \"NoSuchKey\" in bindings");
+
+    let e: Error = DisplayError::NoSuchMonitor(3).into();
+    assert_eq!(e.to_string(), "No monitor with index 3");
+    assert!(e.source().is_none());
+}
+
+#[test]
+fn context_attaches_the_path() {
+    let path = output_path("errors", "missing.txt");
+    let e = std::fs::read_to_string(&path).context(path.display()).unwrap_err();
+    assert!(matches!(&e, Error::Io { path: p, .. } if *p == path.display().to_string()));
+    assert_eq!(e.to_string(), format!("Failed to access {}", path.display()));
+
+    let e = image::open(&path).map(drop).context("missing.txt").unwrap_err();
+    assert!(matches!(e, Error::Image { path, .. } if path == "missing.txt"));
+}
+
+#[test]
+fn only_panic_reports_have_a_backtrace() {
+    let e = Config::load(output_path("errors", "missing.toml")).unwrap_err();
+    let report = CrashReport::from_error(&e);
+    assert!(report.backtrace.is_none());
+    assert_eq!(report.causes.len(), 1);
+    assert!(!report.to_string().contains("Backtrace:"));
+
+    let report = crash::catch(|| panic!("boom")).unwrap_err();
+    assert_eq!(report.message, "boom");
+    assert!(report.backtrace.is_some());
+    assert!(report.to_string().contains("Backtrace:"));
+}
@@ -0,0 +1,147 @@
+// Renders small scenes with the shaders in assets/shaders and compares them to tests/golden.
+// See tests/common/mod.rs for how to update the references.
+mod common;
+
+use std::{cell::RefCell, error::Error, rc::Rc};
+
+use gloom_rs::{
+    glm,
+    gl_utils::{
+        triangle::Triangle,
+        shaders::program::{Program, ProgramBuilder}
+    },
+    scene::node::SceneNode,
+    input::actions::Actions,
+    app::{App, AppContext}
+};
+use image::{Rgba, RgbaImage};
+
+use common::{assert_golden, compare, golden_config, output_path, render, FullscreenApp, Tolerance};
+
+// The five triangles from the triangles example, hung off a rotated parent node and seen
+// through a perspective camera
+#[derive(Default)]
+struct TrianglesApp {
+    root: Option<SceneNode>,
+    program: Option<Rc<Program>>,
+}
+
+impl App for TrianglesApp {
+    fn init(&mut self, ctx: &AppContext) -> Result<(), Box<dyn Error>> {
+        let vertices: Vec<f32> = (0..5).flat_map(|i| {
+            let x = -0.5 + i as f32 * 0.2;
+            vec![x, -0.5, 0.0, x + 0.2, -0.5, 0.0, x + 0.1, 0.5, 0.0]
+        }).collect();
+        let indices: Vec<u32> = (0..15).collect();
+        let mesh = Rc::new(Triangle::init(&vertices, &indices)?);
+
+        let mut program = ProgramBuilder::new()
+            .attach_file("assets/shaders/main.vert")?
+            .attach_file("assets/shaders/main.frag")?
+            .link()?;
+        for uniform in ["projection", "c_trans", "elapsed"].iter() {
+            program.locate_uniform(uniform)?;
+        }
+        let projection = glm::perspective(ctx.aspect_ratio(), 45.0_f32.to_radians(), 0.1, 100.0);
+        program.set_uniform("projection", projection)?;
+        let program = Rc::new(program);
+
+        let mut parent = SceneNode::new("parent");
+        parent.rotation = glm::vec3(0.3, 0.0, 0.2);
+        parent.add_child(SceneNode::with_mesh("triangles", mesh, Rc::clone(&program)));
+        let mut root = SceneNode::new("root");
+        root.add_child(parent);
+        root.update_world_transforms(&glm::identity());
+
+        self.root = Some(root);
+        self.program = Some(program);
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &AppContext, _delta_time: f32, _actions: &Actions) { }
+
+    fn render(&mut self, ctx: &AppContext) {
+        let program = self.program.as_ref().unwrap();
+        program.set_uniform("elapsed", ctx.elapsed).unwrap();
+        let view = glm::translation(&glm::vec3(0.0, 0.0, -2.0));
+        self.root.as_ref().unwrap().draw(&view).unwrap();
+    }
+}
+
+#[test]
+fn checker() {
+    if let Some(image) = render(&golden_config(0.0), || FullscreenApp::new("assets/shaders/simple_checker.frag")) {
+        assert_golden("checker", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn elapsed_pinned() {
+    if let Some(image) = render(&golden_config(2.5), || FullscreenApp::new("assets/shaders/simple_elapsed.frag")) {
+        assert_golden("elapsed_2.5", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn triangles_scene() {
+    if let Some(image) = render(&golden_config(1.5), TrianglesApp::default) {
+        assert_golden("triangles_1.5", &image, Tolerance::default());
+    }
+}
+
+// Keeps the elapsed time of every frame it updates
+struct ClockApp {
+    times: Rc<RefCell<Vec<f32>>>,
+}
+
+impl App for ClockApp {
+    fn init(&mut self, _ctx: &AppContext) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &AppContext, delta_time: f32, _actions: &Actions) {
+        assert_eq!(delta_time, 0.1);
+        self.times.borrow_mut().push(ctx.elapsed);
+    }
+
+    fn render(&mut self, _ctx: &AppContext) { }
+}
+
+#[test]
+fn headless_recording_steps_at_the_recording_frame_rate() {
+    let mut config = golden_config(0.0);
+    config.headless.frames = 3;
+    config.recording.enabled = true;
+    config.recording.frame_rate = 10.0;
+    config.recording.directory = output_path("recording", "frames").display().to_string();
+
+    let times = Rc::new(RefCell::new(Vec::new()));
+    let app_times = Rc::clone(&times);
+    if render(&config, move || ClockApp { times: app_times }).is_some() {
+        assert_eq!(*times.borrow(), [0.0, 0.1, 0.2]);
+    }
+}
+
+#[test]
+fn compare_ignores_small_differences() {
+    let expected = RgbaImage::from_pixel(8, 8, Rgba([100, 150, 200, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(3, 3, Rgba([102, 149, 200, 255]));
+    actual.put_pixel(4, 4, Rgba([104, 150, 200, 255]));
+
+    let comparison = compare(&actual, &expected, Tolerance::default());
+    assert_eq!(comparison.failing_pixels, 0);
+    assert_eq!(*comparison.diff.get_pixel(4, 4), Rgba([255, 255, 0, 255]));
+}
+
+#[test]
+fn compare_finds_visible_differences() {
+    let expected = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(1, 2, Rgba([255, 255, 255, 255]));
+
+    let comparison = compare(&actual, &expected, Tolerance::default());
+    assert_eq!(comparison.failing_pixels, 1);
+    assert!(comparison.max_difference > 0.9);
+    assert_eq!(*comparison.diff.get_pixel(1, 2), Rgba([255, 0, 0, 255]));
+}
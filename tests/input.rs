@@ -0,0 +1,88 @@
+// Per-frame input state built from the events the event loop forwards
+use glutin::event::{ElementState::{Pressed, Released}, ModifiersState, MouseButton, VirtualKeyCode::*};
+
+use gloom_rs::input::{
+    actions::Actions,
+    bindings::{Binding, Bindings},
+    event::InputEvent,
+    state::InputState
+};
+
+#[test]
+fn presses_and_releases_last_one_frame() {
+    let mut input = InputState::new();
+    input.begin_frame();
+    input.process(InputEvent::Key { key: W, state: Pressed });
+    input.process(InputEvent::MouseButton { button: MouseButton::Left, state: Pressed });
+    assert!(input.is_key_pressed(W) && input.is_key_down(W));
+    assert!(input.is_button_pressed(MouseButton::Left) && input.is_button_down(MouseButton::Left));
+
+    // Holding a key repeats the press event, which doesn't count as a new press
+    input.begin_frame();
+    input.process(InputEvent::Key { key: W, state: Pressed });
+    assert!(!input.is_key_pressed(W) && input.is_key_down(W));
+    assert!(!input.is_button_pressed(MouseButton::Left));
+
+    input.begin_frame();
+    input.process(InputEvent::Key { key: W, state: Released });
+    input.process(InputEvent::MouseButton { button: MouseButton::Left, state: Released });
+    assert!(input.is_key_released(W) && !input.is_key_down(W));
+    assert!(input.is_button_released(MouseButton::Left) && !input.is_button_down(MouseButton::Left));
+}
+
+#[test]
+fn mouse_motion_and_scroll_add_up_over_a_frame() {
+    let mut input = InputState::new();
+    input.begin_frame();
+    input.process(InputEvent::CursorMoved { x: 10.0, y: 20.0 });
+    input.process(InputEvent::CursorMoved { x: 12.0, y: 25.0 });
+    input.process(InputEvent::MouseMotion { delta_x: 1.0, delta_y: -2.0 });
+    input.process(InputEvent::MouseMotion { delta_x: 3.0, delta_y: 1.0 });
+    input.process(InputEvent::Scroll { x: 0.0, y: 1.0 });
+    input.process(InputEvent::Scroll { x: 0.0, y: 0.5 });
+    assert_eq!(input.cursor_position(), (12.0, 25.0));
+    assert_eq!(input.mouse_delta(), (4.0, -1.0));
+    assert_eq!(input.scroll(), (0.0, 1.5));
+
+    // The cursor stays where it is, motion and scroll start over
+    input.begin_frame();
+    assert_eq!(input.cursor_position(), (12.0, 25.0));
+    assert_eq!(input.mouse_delta(), (0.0, 0.0));
+    assert_eq!(input.scroll(), (0.0, 0.0));
+}
+
+#[test]
+fn losing_focus_releases_everything() {
+    let mut input = InputState::new();
+    input.begin_frame();
+    input.process(InputEvent::Key { key: LShift, state: Pressed });
+    input.process(InputEvent::Modifiers(ModifiersState::SHIFT));
+    input.process(InputEvent::MouseButton { button: MouseButton::Right, state: Pressed });
+    assert_eq!(input.modifiers(), ModifiersState::SHIFT);
+
+    input.begin_frame();
+    input.process(InputEvent::Focused(false));
+    assert!(input.is_key_released(LShift) && !input.is_key_down(LShift));
+    assert!(input.is_button_released(MouseButton::Right));
+    assert_eq!(input.modifiers(), ModifiersState::empty());
+}
+
+#[test]
+fn actions_and_axes_follow_their_bindings() {
+    let mut bindings = Bindings::default();
+    bindings.bind_action("jump", vec![Binding::Key(Space), Binding::Mouse(MouseButton::Middle)]);
+    let mut input = InputState::new();
+    input.begin_frame();
+    input.process(InputEvent::MouseButton { button: MouseButton::Middle, state: Pressed });
+    input.process(InputEvent::Key { key: W, state: Pressed });
+    input.process(InputEvent::Key { key: D, state: Pressed });
+    input.process(InputEvent::Key { key: A, state: Pressed });
+
+    let actions = Actions::new(&input, &bindings);
+    assert!(actions.is_pressed("jump") && actions.is_down("jump"));
+    assert!(!actions.is_down("unbound"));
+    assert_eq!(actions.axis("move_forward"), 1.0);
+    // Both directions cancel out
+    assert_eq!(actions.axis("move_right"), 0.0);
+    assert_eq!(actions.axis("unbound"), 0.0);
+}
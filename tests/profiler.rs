@@ -0,0 +1,156 @@
+// The GPU profiler against a RecordingBackend with a simulated GPU clock
+use std::{collections::BTreeSet, rc::Rc, time::Duration};
+
+use gloom_rs::gl_utils::{
+    backend::{self, GlCall, RecordingBackend},
+    capabilities::{Capabilities, Limits},
+    profiler::{GpuProfiler, FRAMES_IN_FLIGHT}
+};
+
+fn capabilities(major: u32, minor: u32) -> Capabilities {
+    Capabilities {
+        version: format!("{}.{}", major, minor),
+        major,
+        minor,
+        vendor: "test".to_string(),
+        renderer: "test".to_string(),
+        glsl_version: "test".to_string(),
+        extensions: BTreeSet::new(),
+        limits: Limits::default(),
+    }
+}
+
+// One frame with a 1 ms "shadows" pass and a 3 ms "scene" pass with a 2 ms "opaque" pass inside
+fn profiled_frame(profiler: &GpuProfiler, gpu: &RecordingBackend) {
+    profiler.begin_frame();
+    {
+        let _shadows = profiler.scope("shadows");
+        gpu.advance_gpu_time(1_000_000);
+    }
+    {
+        let _scene = profiler.scope("scene");
+        {
+            let _opaque = profiler.scope("opaque");
+            gpu.advance_gpu_time(2_000_000);
+        }
+        gpu.advance_gpu_time(1_000_000);
+    }
+    gpu.advance_gpu_time(500_000);
+    profiler.end_frame();
+}
+
+#[test]
+fn results_are_read_when_the_next_frame_begins() {
+    let gpu = Rc::new(RecordingBackend::new());
+    let _guard = backend::install(gpu.clone());
+    let profiler = GpuProfiler::new(&capabilities(3, 3));
+    assert!(profiler.is_enabled());
+
+    profiled_frame(&profiler, &gpu);
+    assert_eq!(profiler.latest(), None);
+    profiler.begin_frame();
+    let frame = profiler.latest().unwrap();
+    profiler.end_frame();
+
+    assert_eq!(frame.frame, 0);
+    assert_eq!(frame.total, Duration::from_micros(4500));
+    let names: Vec<(&str, usize)> = frame.passes.iter().map(|pass| (pass.name.as_str(), pass.depth)).collect();
+    assert_eq!(names, [("shadows", 0), ("scene", 0), ("opaque", 1)]);
+    assert_eq!(frame.pass("shadows").unwrap().duration, Duration::from_millis(1));
+    assert_eq!(frame.pass("scene").unwrap().duration, Duration::from_millis(3));
+    assert_eq!(frame.pass("scene").unwrap().start, Duration::from_millis(1));
+    assert_eq!(frame.pass("opaque").unwrap().duration, Duration::from_millis(2));
+    assert_eq!(profiler.dropped_frames(), 0);
+}
+
+#[test]
+fn queries_are_reused_and_deleted() {
+    let gpu = Rc::new(RecordingBackend::new());
+    let _guard = backend::install(gpu.clone());
+    let profiler = GpuProfiler::new(&capabilities(4, 5));
+
+    for _ in 0..10 {
+        profiled_frame(&profiler, &gpu);
+    }
+    // Every frame uses 8 timestamps, and only FRAMES_IN_FLIGHT frames are ever in flight
+    let created: usize = gpu.calls().iter()
+        .map(|call| match call {
+            GlCall::GenQueries(ids) => ids.len(),
+            _ => 0,
+        })
+        .sum();
+    assert!(created <= 8 * (FRAMES_IN_FLIGHT + 1), "created {} queries", created);
+    assert!(gpu.calls().contains(&GlCall::QueryCounter { id: 1, target: gl::TIMESTAMP }));
+
+    drop(profiler);
+    assert_eq!(gpu.live_objects(), 0);
+}
+
+#[test]
+fn slow_frames_are_dropped_instead_of_waited_for() {
+    let gpu = Rc::new(RecordingBackend::new());
+    let _guard = backend::install(gpu.clone());
+    let profiler = GpuProfiler::new(&capabilities(3, 3));
+
+    gpu.hold_query_results(true);
+    for _ in 0..FRAMES_IN_FLIGHT + 2 {
+        profiled_frame(&profiler, &gpu);
+    }
+    assert_eq!(profiler.latest(), None);
+    assert_eq!(profiler.dropped_frames(), 2);
+
+    // finish waits for whatever is still in flight
+    gpu.hold_query_results(false);
+    profiler.finish();
+    assert_eq!(profiler.latest().unwrap().frame, FRAMES_IN_FLIGHT as u64 + 1);
+}
+
+#[test]
+fn open_passes_end_with_the_frame() {
+    let gpu = Rc::new(RecordingBackend::new());
+    let _guard = backend::install(gpu.clone());
+    let profiler = GpuProfiler::new(&capabilities(3, 3));
+
+    // Outside of a frame, passes aren't timed
+    profiler.begin_pass("init");
+    profiler.end_pass();
+
+    profiler.begin_frame();
+    profiler.begin_pass("unfinished");
+    gpu.advance_gpu_time(1_000);
+    profiler.end_frame();
+    profiler.finish();
+
+    let frame = profiler.latest().unwrap();
+    assert_eq!(frame.passes.len(), 1);
+    assert_eq!(frame.pass("unfinished").unwrap().duration, Duration::from_micros(1));
+}
+
+#[test]
+fn unsupported_or_disabled_profilers_make_no_calls() {
+    let gpu = Rc::new(RecordingBackend::new());
+    let _guard = backend::install(gpu.clone());
+
+    for profiler in [GpuProfiler::new(&capabilities(3, 2)), GpuProfiler::disabled()] {
+        assert!(!profiler.is_enabled());
+        profiled_frame(&profiler, &gpu);
+        profiler.finish();
+        assert_eq!(profiler.latest(), None);
+    }
+    assert!(gpu.calls().is_empty());
+}
+
+#[test]
+fn breakdown_is_indented_by_depth() {
+    let gpu = Rc::new(RecordingBackend::new());
+    let _guard = backend::install(gpu.clone());
+    let profiler = GpuProfiler::new(&capabilities(3, 3));
+    profiled_frame(&profiler, &gpu);
+    profiler.finish();
+
+    let breakdown = profiler.latest().unwrap().to_string();
+    let lines: Vec<&str> = breakdown.lines().collect();
+    assert_eq!(lines[0], "GPU frame 0: 4.500 ms");
+    assert_eq!(lines[1], "  shadows                       1.000 ms");
+    assert_eq!(lines[3], "    opaque                      2.000 ms");
+}
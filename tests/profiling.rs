@@ -0,0 +1,104 @@
+// CPU spans and their Chrome trace export. Recording is global, so the tests take turns
+mod common;
+
+use std::{sync::Mutex, thread};
+
+use gloom_rs::profiling::{self, CpuCapture, CpuProfileConfig, Profile};
+
+use common::output_path;
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial() -> std::sync::MutexGuard<'static, ()> {
+    SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[test]
+fn spans_nest_and_keep_their_thread() {
+    let _serial = serial();
+    profiling::start();
+    {
+        let _outer = profiling::span("outer");
+        let _inner = profiling::span("inner");
+    }
+    thread::Builder::new().name("worker".to_string())
+        .spawn(|| drop(profiling::span(format!("job {}", 1))))
+        .unwrap().join().unwrap();
+    let profile = profiling::stop().unwrap();
+
+    let spans: Vec<&str> = profile.spans().map(|span| span.name.as_str()).collect();
+    assert_eq!(spans, ["inner", "outer", "job 1"]);
+
+    let span = |name: &str| profile.spans().find(|span| span.name == name).unwrap();
+    let (outer, inner, job) = (span("outer"), span("inner"), span("job 1"));
+    assert!(outer.ts <= inner.ts);
+    assert!(inner.ts + inner.dur.unwrap() <= outer.ts + outer.dur.unwrap());
+    assert_eq!(outer.tid, inner.tid);
+    assert_ne!(outer.tid, job.tid);
+
+    let worker = profile.trace_events.iter()
+        .find(|event| event.ph == "M" && event.tid == job.tid)
+        .unwrap();
+    assert_eq!(worker.args.as_ref().unwrap()["name"], "worker");
+    assert!(profile.trace_events.iter().all(|event| event.pid == std::process::id()));
+}
+
+#[test]
+fn nothing_is_recorded_outside_a_session() {
+    let _serial = serial();
+    drop(profiling::span("before"));
+    profiling::instant("before");
+    assert!(!profiling::is_recording());
+    assert!(profiling::stop().is_none());
+
+    // Spans that started before recording did aren't recorded either
+    let early = profiling::span("early");
+    profiling::start();
+    drop(early);
+    let profile = profiling::stop().unwrap();
+    assert!(profile.trace_events.is_empty());
+}
+
+#[test]
+fn capture_saves_after_enough_frames() {
+    let _serial = serial();
+    let path = output_path("profiling", "capture.json");
+    let _ = std::fs::remove_file(&path);
+    let config = CpuProfileConfig { enabled: true, frames: 2, output: path.display().to_string() };
+
+    let mut capture = CpuCapture::start(&config);
+    for frame in 0..3 {
+        drop(profiling::span("frame"));
+        let saved = capture.end_frame().unwrap();
+        assert_eq!(saved.is_some(), frame == 1);
+    }
+    assert!(!profiling::is_recording());
+    assert_eq!(capture.finish().unwrap(), None);
+
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(json.starts_with("{\"traceEvents\":["));
+    assert!(json.contains("\"displayTimeUnit\":\"ms\""));
+
+    let profile = Profile::load(&path).unwrap();
+    assert_eq!(profile.spans().count(), 2);
+    let markers: Vec<&str> = profile.trace_events.iter()
+        .filter(|event| event.ph == "i")
+        .map(|event| event.name.as_str())
+        .collect();
+    assert_eq!(markers, ["frame 0", "frame 1"]);
+}
+
+#[test]
+fn finishing_early_saves_what_was_recorded() {
+    let _serial = serial();
+    let path = output_path("profiling", "early.json");
+    let config = CpuProfileConfig { enabled: true, frames: 100, output: path.display().to_string() };
+
+    let mut capture = CpuCapture::start(&config);
+    drop(profiling::span("only frame"));
+    assert_eq!(capture.end_frame().unwrap(), None);
+    assert_eq!(capture.finish().unwrap(), Some(path.display().to_string()));
+
+    let profile = Profile::load(&path).unwrap();
+    assert_eq!(profile.spans().next().unwrap().name, "only frame");
+}
@@ -0,0 +1,107 @@
+// Frame time statistics, without rendering anything
+mod common;
+
+use gloom_rs::{
+    config::Config,
+    stats::{Benchmark, FrameLog, FrameStats, RollingWindow, StatsConfig, Summary}
+};
+
+use common::output_path;
+
+#[test]
+fn summary_of_frame_times() {
+    // 1 to 100 ms, shuffled
+    let times: Vec<f32> = (1..=100).map(|i| ((i * 37) % 100 + 1) as f32 / 1000.0).collect();
+    let summary = Summary::from_frame_times(&times).unwrap();
+
+    assert_eq!(summary.frames, 100);
+    assert_eq!(summary.min, 0.001);
+    assert_eq!(summary.max, 0.1);
+    assert_eq!(summary.p50, 0.05);
+    assert_eq!(summary.p95, 0.095);
+    assert_eq!(summary.p99, 0.099);
+    assert!((summary.mean - 0.0505).abs() < 1e-6);
+    assert!((summary.fps() - 1.0 / 0.0505).abs() < 1e-3);
+
+    assert_eq!(Summary::from_frame_times(&[]), None);
+    let single = Summary::from_frame_times(&[0.02]).unwrap();
+    assert_eq!((single.p50, single.p99), (0.02, 0.02));
+}
+
+#[test]
+fn rolling_window_forgets_old_frames() {
+    let mut window = RollingWindow::new(3);
+    assert!(window.summary().is_none());
+    for time in [0.5, 0.01, 0.02, 0.03] {
+        window.push(time);
+    }
+
+    let summary = window.summary().unwrap();
+    assert_eq!(window.len(), 3);
+    assert_eq!(summary.max, 0.03);
+    assert_eq!(summary.min, 0.01);
+}
+
+#[test]
+fn benchmark_skips_warmup_frames() {
+    let mut benchmark = Benchmark::new(3, 2);
+    assert_eq!(benchmark.total_frames(), 5);
+    for time in [1.0, 1.0, 0.01, 0.02] {
+        benchmark.record(time);
+        assert!(!benchmark.is_done());
+    }
+    benchmark.record(0.03);
+    benchmark.record(5.0);
+
+    assert!(benchmark.is_done());
+    let summary = benchmark.summary().unwrap();
+    assert_eq!(summary.frames, 3);
+    assert_eq!(summary.max, 0.03);
+}
+
+#[test]
+fn frame_log_writes_csv() {
+    let path = output_path("stats", "frames.csv");
+    let mut log = FrameLog::create(&path).unwrap();
+    log.log(0, 0.0, 0.016).unwrap();
+    log.log(1, 0.016, 0.0175).unwrap();
+    log.finish().unwrap();
+
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines, ["frame,elapsed,frame_time_ms", "0,0.000000,16.0000", "1,0.016000,17.5000"]);
+}
+
+#[test]
+fn title_updates_once_per_interval() {
+    let config = StatsConfig { show_in_title: true, title_interval: 0.05, ..StatsConfig::default() };
+    let mut stats = FrameStats::new(&config).unwrap();
+
+    stats.end_frame(0.02, 0.0);
+    assert_eq!(stats.title("Gloom"), None);
+    stats.end_frame(0.02, 0.02);
+    stats.end_frame(0.02, 0.04);
+    assert_eq!(stats.title("Gloom").as_deref(), Some("Gloom - 50 fps (20.00 ms, max 20.00 ms)"));
+    assert_eq!(stats.title("Gloom"), None);
+    assert_eq!(stats.frames(), 3);
+
+    let mut hidden = FrameStats::new(&StatsConfig::default()).unwrap();
+    hidden.end_frame(1.0, 0.0);
+    assert_eq!(hidden.title("Gloom"), None);
+}
+
+#[test]
+fn stats_flags() {
+    let mut config = Config::default();
+    let args: Vec<String> = ["--show-fps", "--frame-times", "times.csv", "--benchmark", "500", "--warmup", "10"]
+        .iter().map(|arg| arg.to_string()).collect();
+    assert!(config.apply_args(&args).is_ok());
+
+    assert!(config.stats.show_in_title);
+    assert_eq!(config.stats.csv.as_deref(), Some("times.csv"));
+    assert_eq!(config.stats.benchmark, Some(500));
+    assert_eq!(config.stats.warmup, 10);
+
+    config.stats.benchmark = Some(0);
+    assert!(config.validate().is_err());
+}
@@ -0,0 +1,269 @@
+// Captures GL traces and replays them, through a RecordingBackend and against a real context
+mod common;
+
+use std::rc::Rc;
+
+use gloom_rs::{
+    gl,
+    error::Error,
+    gl_utils::{
+        backend::{self, GlBackend, GlCall, NativeGl, RecordingBackend, TracingBackend},
+        bindable::Bindable,
+        framebuffer::Framebuffer,
+        triangle::Triangle,
+        shaders::{program::ProgramBuilder, shader_type::ShaderType, uniform::Uniform}
+    },
+    headless::HeadlessContext,
+    screenshot::ScreenshotCapture,
+    trace::{self, Trace}
+};
+
+use common::{compare, golden_config, output_path, render, FullscreenApp, Tolerance};
+
+// Create a triangle and a program, set a uniform and draw, with every call traced
+fn traced_calls(into: Rc<dyn GlBackend>) -> Vec<GlCall> {
+    let tracer = Rc::new(TracingBackend::new(into));
+    let _guard = backend::install(tracer.clone());
+
+    let triangle = Triangle::init(&[0.0; 9], &[0, 1, 2]).unwrap();
+    let mut program = ProgramBuilder::new()
+        .compile_shader("void main() {}", ShaderType::Vertex).unwrap()
+        .link().unwrap();
+    program.locate_uniform("scale").unwrap();
+    program.locate_uniform("elapsed").unwrap();
+    program.set_uniform("elapsed", 2.0).unwrap();
+    program.bind();
+    triangle.draw();
+    program.unbind();
+
+    tracer.calls()
+}
+
+#[test]
+fn tracing_forwards_and_records_every_call() {
+    let recording = Rc::new(RecordingBackend::new());
+    let calls = traced_calls(recording.clone());
+
+    // Everything reached the inner backend, the trace also has the location lookups
+    let without_lookups: Vec<GlCall> = calls.iter()
+        .filter(|call| !matches!(call, GlCall::GetUniformLocation { .. }))
+        .cloned()
+        .collect();
+    assert_eq!(without_lookups, recording.calls()[..without_lookups.len()]);
+    assert!(calls.contains(&GlCall::GetUniformLocation { program: 4, name: "elapsed".to_string(), location: 1 }));
+    assert!(calls.contains(&GlCall::Uniform { program: 4, location: 1, value: Uniform::Float(2.0) }));
+}
+
+#[test]
+fn stopped_tracer_keeps_forwarding() {
+    let recording = Rc::new(RecordingBackend::new());
+    let tracer = TracingBackend::new(recording.clone());
+    tracer.enable(gl::BLEND);
+    tracer.stop();
+    tracer.disable(gl::BLEND);
+
+    assert_eq!(tracer.calls(), vec![GlCall::Enable(gl::BLEND)]);
+    assert_eq!(recording.calls(), vec![GlCall::Enable(gl::BLEND), GlCall::Disable(gl::BLEND)]);
+}
+
+#[test]
+fn replay_translates_ids_and_locations() {
+    let calls = traced_calls(Rc::new(RecordingBackend::new()));
+
+    // Objects on the replaying side get different ids than the traced ones
+    let target = RecordingBackend::new();
+    target.gen_buffers(&mut [0; 3]);
+    target.create_program();
+    target.take_calls();
+
+    let report = trace::replay(&calls, &target);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    assert_eq!(report.calls, calls.len());
+
+    let replayed = target.calls();
+    let program = match replayed.iter().find(|call| matches!(call, GlCall::CreateProgram(_))) {
+        Some(GlCall::CreateProgram(id)) => *id,
+        _ => panic!("no program was created"),
+    };
+    assert_ne!(program, 4);
+    let location = target.uniform_location(program, "elapsed").unwrap();
+    assert!(replayed.contains(&GlCall::Uniform { program, location, value: Uniform::Float(2.0) }));
+
+    let buffers = match replayed.iter().find(|call| matches!(call, GlCall::GenBuffers(_))) {
+        Some(GlCall::GenBuffers(ids)) => ids.clone(),
+        _ => panic!("no buffers were created"),
+    };
+    assert!(replayed.contains(&GlCall::BindBuffer { target: gl::ELEMENT_ARRAY_BUFFER, id: buffers[1] }));
+    assert_eq!(report.programs, vec![(4, program)]);
+}
+
+#[test]
+fn replay_reports_differences() {
+    let calls = traced_calls(Rc::new(RecordingBackend::new()));
+
+    let target = RecordingBackend::new();
+    target.remove_uniform("elapsed");
+    target.fail_compile("main", "0:1(1): error: unsupported");
+    let report = trace::replay(&calls, &target);
+
+    assert!(report.warnings.iter().any(|warning| warning.contains("failed to compile") && warning.contains("unsupported")));
+    assert!(report.warnings.iter().any(|warning| warning.contains("\"elapsed\"")));
+    assert!(target.calls().iter().all(|call| !matches!(call, GlCall::Uniform { .. })));
+}
+
+#[test]
+fn traces_survive_saving_and_loading() {
+    let trace = Trace {
+        width: 64,
+        height: 48,
+        frame: 3,
+        frame_start: 2,
+        renderer: "test".to_string(),
+        gl_version: "4.5".to_string(),
+        calls: traced_calls(Rc::new(RecordingBackend::new())),
+    };
+    let path = output_path("trace", "roundtrip.bin");
+    trace.save(&path).unwrap();
+
+    let loaded = Trace::load(&path).unwrap();
+    assert_eq!((loaded.width, loaded.height, loaded.frame), (64, 48, 3));
+    assert_eq!(loaded.calls, trace.calls);
+    assert_eq!(loaded.frame_calls(), &trace.calls[2..]);
+
+    std::fs::write(&path, [9, 0, 0, 0]).unwrap();
+    assert!(matches!(Trace::load(&path), Err(Error::Trace { .. })));
+}
+
+#[test]
+fn replayed_frame_matches_the_original() {
+    let path = output_path("trace", "checker.bin");
+    let mut config = golden_config(0.0);
+    config.headless.frames = 2;
+    config.trace.enabled = true;
+    config.trace.frame = 1;
+    config.trace.output = path.display().to_string();
+
+    let original = match render(&config, || FullscreenApp::new("assets/shaders/simple_checker.frag")) {
+        Some(image) => image,
+        None => return,
+    };
+    let trace = Trace::load(&path).unwrap();
+    assert_eq!(trace.frame, 1);
+    assert!(trace.frame_calls().iter().any(|call| matches!(call, GlCall::DrawElements { .. })));
+
+    let context = HeadlessContext::new(false).unwrap();
+    let framebuffer = Framebuffer::new(trace.width, trace.height, 0).unwrap();
+    framebuffer.bind();
+    let report = trace::replay(&trace.calls, &NativeGl);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    let replayed = framebuffer.read_pixels().unwrap();
+    drop(framebuffer);
+    drop(context);
+
+    let comparison = compare(&replayed, &original, Tolerance::default());
+    assert_eq!(comparison.failing_pixels, 0);
+}
+
+#[test]
+fn native_gl_refuses_offsets_without_a_bound_buffer() {
+    let context = HeadlessContext::new(false).unwrap();
+    let refused = |call: &dyn Fn()| std::panic::catch_unwind(std::panic::AssertUnwindSafe(call)).is_err();
+    // A fresh context has nothing bound, so each offset would be taken as a pointer
+    assert!(refused(&|| NativeGl.read_pixels_to_buffer(0, 0, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE, 0)));
+    assert!(refused(&|| NativeGl.draw_elements(gl::TRIANGLES, 3, gl::UNSIGNED_INT, 0)));
+    assert!(refused(&|| NativeGl.vertex_attrib_pointer(0, 3, gl::FLOAT, false, 12, 0)));
+    drop(context);
+}
+
+// Draw into a multisampled framebuffer, read it back and take a screenshot, with every call traced
+fn traced_offscreen_calls(into: Rc<dyn GlBackend>) -> Vec<GlCall> {
+    let tracer = Rc::new(TracingBackend::new(into));
+    let _guard = backend::install(tracer.clone());
+
+    let framebuffer = Framebuffer::new(8, 4, 4).unwrap();
+    framebuffer.bind();
+    backend::current().clear(gl::COLOR_BUFFER_BIT);
+    framebuffer.read_pixels().unwrap();
+    let mut screenshots = ScreenshotCapture::new(output_path("trace", "screenshots"), false);
+    screenshots.capture_to(8, 4, output_path("trace", "screenshots").join("offscreen.png")).unwrap();
+    screenshots.finish();
+    drop(screenshots);
+    // Leave the frame in the framebuffer, like the headless renderer does
+    framebuffer.bind();
+    tracer.calls()
+}
+
+#[test]
+fn framebuffers_readback_and_fences_are_traced() {
+    let calls = traced_offscreen_calls(Rc::new(RecordingBackend::new()));
+    assert!(calls.contains(&GlCall::GenFramebuffers(vec![1])));
+    assert!(calls.iter().any(|call| matches!(call, GlCall::RenderbufferStorageMultisample { samples: 4, width: 8, height: 4, .. })));
+    assert!(calls.iter().any(|call| matches!(call, GlCall::BlitFramebuffer { .. })));
+    assert!(calls.iter().any(|call| matches!(call, GlCall::ReadPixels { width: 8, height: 4, .. })));
+    assert!(calls.iter().any(|call| matches!(call, GlCall::ReadPixelsToBuffer { width: 8, height: 4, .. })));
+    assert!(calls.iter().any(|call| matches!(call, GlCall::FenceSync(_))));
+    assert!(calls.iter().any(|call| matches!(call, GlCall::DeleteSync(_))));
+}
+
+#[test]
+fn replay_copies_offscreen_frames_into_the_bound_framebuffer() {
+    let calls = traced_offscreen_calls(Rc::new(RecordingBackend::new()));
+
+    let target = RecordingBackend::new();
+    let mut caller = 0;
+    target.gen_framebuffers(std::slice::from_mut(&mut caller));
+    target.gen_framebuffers(&mut [0; 2]);
+    target.bind_framebuffer(gl::FRAMEBUFFER, caller);
+    target.take_calls();
+
+    let report = trace::replay(&calls, &target);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    let replayed = target.calls();
+    let draw = match replayed.iter().find(|call| matches!(call, GlCall::GenFramebuffers(_))) {
+        Some(GlCall::GenFramebuffers(ids)) => ids[0],
+        _ => panic!("no framebuffer was created"),
+    };
+    assert_ne!(draw, caller);
+    let end = &replayed[replayed.len() - 4..];
+    assert_eq!(end, [
+        GlCall::BindFramebuffer { target: gl::READ_FRAMEBUFFER, id: draw },
+        GlCall::BindFramebuffer { target: gl::DRAW_FRAMEBUFFER, id: caller },
+        GlCall::BlitFramebuffer { source: [0, 0, 8, 4], destination: [0, 0, 8, 4], mask: gl::COLOR_BUFFER_BIT, filter: gl::NEAREST },
+        GlCall::BindFramebuffer { target: gl::FRAMEBUFFER, id: caller },
+    ]);
+}
+
+#[test]
+fn replay_warns_about_framebuffers_missing_from_the_trace() {
+    let calls = vec![GlCall::BindFramebuffer { target: gl::FRAMEBUFFER, id: 3 }, GlCall::Clear(gl::COLOR_BUFFER_BIT)];
+    let report = trace::replay(&calls, &RecordingBackend::new());
+    assert!(report.warnings.iter().any(|warning| warning.contains("the trace is incomplete")), "{:?}", report.warnings);
+}
+
+#[test]
+fn replayed_multisampled_frame_matches_the_original() {
+    let path = output_path("trace", "checker-msaa.bin");
+    let mut config = golden_config(0.0);
+    config.context.msaa = 4;
+    config.trace.enabled = true;
+    config.trace.output = path.display().to_string();
+
+    let original = match render(&config, || FullscreenApp::new("assets/shaders/simple_checker.frag")) {
+        Some(image) => image,
+        None => return,
+    };
+    let trace = Trace::load(&path).unwrap();
+    assert!(trace.calls.iter().any(|call| matches!(call, GlCall::RenderbufferStorageMultisample { samples: 4, .. })));
+
+    let context = HeadlessContext::new(false).unwrap();
+    let framebuffer = Framebuffer::new(trace.width, trace.height, 0).unwrap();
+    framebuffer.bind();
+    let report = trace::replay(&trace.calls, &NativeGl);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    let replayed = framebuffer.read_pixels().unwrap();
+    drop(framebuffer);
+    drop(context);
+
+    let comparison = compare(&replayed, &original, Tolerance::default());
+    assert_eq!(comparison.failing_pixels, 0);
+}